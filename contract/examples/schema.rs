@@ -3,8 +3,9 @@ use std::fs::create_dir_all;
 
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
-use memory_match_contract::msg::{CardResponse, HandleMsg, InitMsg, MatchResponse, PlayerResponse, QueryMsg};
-use memory_match_contract::state::{Card, Color, Match, Player, Random, Shape};
+use memory_match_contract::msg::{BankrollResponse, CardResponse, ConfigResponse, ContractStatusResponse, DailyResultsResponse, HandleMsg, InitMsg, LeaderboardResponse, MatchAuditResponse, MatchReplayResponse, MatchResponse, MatchesResponse, OpenGamesResponse, PlayerResponse, QueryMsg, ReceiveMsg, TokenScheduleResponse};
+use memory_match_contract::randomness::{OracleQueryMsg, OracleRandomResponse};
+use memory_match_contract::state::{Card, Color, Config, Match, MatchStatus, Player, Random, Shape};
 
 fn main() {
     let mut out_dir = current_dir().unwrap();
@@ -15,13 +16,28 @@ fn main() {
     export_schema(&schema_for!(InitMsg), &out_dir);
     export_schema(&schema_for!(HandleMsg), &out_dir);
     export_schema(&schema_for!(QueryMsg), &out_dir);
+    export_schema(&schema_for!(ReceiveMsg), &out_dir);
     export_schema(&schema_for!(PlayerResponse), &out_dir);
     export_schema(&schema_for!(CardResponse), &out_dir);
     export_schema(&schema_for!(MatchResponse), &out_dir);
+    export_schema(&schema_for!(MatchAuditResponse), &out_dir);
+    export_schema(&schema_for!(MatchReplayResponse), &out_dir);
+    export_schema(&schema_for!(MatchesResponse), &out_dir);
+    export_schema(&schema_for!(LeaderboardResponse), &out_dir);
+    export_schema(&schema_for!(OpenGamesResponse), &out_dir);
+    export_schema(&schema_for!(DailyResultsResponse), &out_dir);
+    export_schema(&schema_for!(ConfigResponse), &out_dir);
+    export_schema(&schema_for!(ContractStatusResponse), &out_dir);
+    export_schema(&schema_for!(BankrollResponse), &out_dir);
+    export_schema(&schema_for!(TokenScheduleResponse), &out_dir);
+    export_schema(&schema_for!(OracleQueryMsg), &out_dir);
+    export_schema(&schema_for!(OracleRandomResponse), &out_dir);
+    export_schema(&schema_for!(Config), &out_dir);
     export_schema(&schema_for!(Random), &out_dir);
     export_schema(&schema_for!(Player), &out_dir);
     export_schema(&schema_for!(Shape), &out_dir);
     export_schema(&schema_for!(Color), &out_dir);
     export_schema(&schema_for!(Card), &out_dir);
     export_schema(&schema_for!(Match), &out_dir);
+    export_schema(&schema_for!(MatchStatus), &out_dir);
 }
@@ -0,0 +1,164 @@
+//! Achievements minted as SNIP-721 NFTs when a solo match is completed.
+//!
+//! Each earned achievement mints one NFT to the player. Its public metadata
+//! names the achievement, and its private metadata holds the final board and
+//! the score as JSON, so only the owner can see how the board was laid out.
+
+use cosmwasm_std::{to_vec, CosmosMsg, HumanAddr, StdResult};
+use schemars::JsonSchema;
+use secret_toolkit::snip721::{mint_nft_msg, Metadata};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::audit::{layout, CardFace};
+use crate::error::ContractError;
+use crate::state::{ContractInfo, Match, Player};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AchievementKind {
+    Flawless,
+    PersonalBest,
+    MatchCount { count: u32 },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Achievement {
+    pub kind: AchievementKind,
+    pub name: String,
+    pub description: String,
+}
+
+impl Achievement {
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            Achievement {
+                kind: AchievementKind::Flawless,
+                name: "Flawless".to_string(),
+                description: "Completed a board without a single miss.".to_string(),
+            },
+            Achievement {
+                kind: AchievementKind::PersonalBest,
+                name: "Personal Best".to_string(),
+                description: "Beat a previous best score on a board size.".to_string(),
+            },
+            Achievement {
+                kind: AchievementKind::MatchCount { count: 100 },
+                name: "Centurion".to_string(),
+                description: "Completed 100 matches.".to_string(),
+            },
+        ]
+    }
+
+    // Kinds must be unique, or a single match would mint the same token id
+    // twice and fail.
+    pub fn validate_all(achievements: &[Self]) -> StdResult<()> {
+        let is_valid = achievements.iter().enumerate().all(|(i, achievement)| {
+            !achievement.name.is_empty()
+                && achievements[..i]
+                    .iter()
+                    .all(|other| other.kind != achievement.kind)
+        });
+        if !is_valid {
+            return Err(ContractError::InvalidAchievements.into());
+        }
+        Ok(())
+    }
+
+    pub fn token_suffix(&self) -> String {
+        match self.kind {
+            AchievementKind::Flawless => "flawless".to_string(),
+            AchievementKind::PersonalBest => "personal_best".to_string(),
+            AchievementKind::MatchCount { count } => format!("match_count_{}", count),
+        }
+    }
+
+    // Per-match achievements are only earned in solo matches, so the match id
+    // keeps their tokens unique, while a match count is awarded once per player.
+    // Token ids are public, so the owner only goes into them hashed.
+    pub fn token_id(&self, match_id: &str, owner: &HumanAddr) -> String {
+        match self.kind {
+            AchievementKind::MatchCount { count } => {
+                let mut hasher = Sha256::new();
+                hasher.update(owner.as_str().as_bytes());
+                hasher.update(count.to_be_bytes());
+                let hash: String = hasher
+                    .finalize()
+                    .iter()
+                    .map(|byte| format!("{:02x}", byte))
+                    .collect();
+                format!("{}-{}", hash, self.token_suffix())
+            }
+            _ => format!("{}-{}", match_id, self.token_suffix()),
+        }
+    }
+
+    // `player` already has the completed match recorded, while
+    // `previous_best` is their best score on the board size from before it.
+    // Matches can complete without being checked, e.g. while no NFT contract
+    // is set, so a match count is earned at or past the count until awarded.
+    pub fn is_earned(
+        &self,
+        game_match: &Match,
+        player: &Player,
+        previous_best: Option<u64>,
+    ) -> bool {
+        match self.kind {
+            AchievementKind::Flawless => game_match.is_solo() && game_match.attempts == 0,
+            AchievementKind::PersonalBest => match (previous_best, game_match.score) {
+                (Some(previous_best), Some(score)) => score > previous_best,
+                _ => false,
+            },
+            AchievementKind::MatchCount { count } => {
+                player.matches_completed >= count && !player.match_count_awards.contains(&count)
+            }
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct AchievementRecord<'a> {
+    achievement: &'a str,
+    match_id: &'a str,
+    size: (u32, u32),
+    score: Option<u64>,
+    board: Vec<Vec<CardFace>>,
+}
+
+pub fn mint_achievement_msg(
+    nft: &ContractInfo,
+    achievement: &Achievement,
+    owner: HumanAddr,
+    match_id: &str,
+    game_match: &Match,
+    block_size: usize,
+) -> StdResult<CosmosMsg> {
+    let record = AchievementRecord {
+        achievement: &achievement.name,
+        match_id,
+        size: game_match.size,
+        score: game_match.score,
+        board: layout(&game_match.cards),
+    };
+    let public_metadata = Metadata {
+        name: Some(achievement.name.clone()),
+        description: Some(achievement.description.clone()),
+        image: None,
+    };
+    let private_metadata = Metadata {
+        name: Some(achievement.name.clone()),
+        description: Some(String::from_utf8_lossy(&to_vec(&record)?).into_owned()),
+        image: None,
+    };
+    mint_nft_msg(
+        Some(achievement.token_id(match_id, &owner)),
+        Some(owner),
+        Some(public_metadata),
+        Some(private_metadata),
+        None,
+        None,
+        block_size,
+        nft.code_hash.clone(),
+        nft.address.clone(),
+    )
+}
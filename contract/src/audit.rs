@@ -0,0 +1,87 @@
+//! Commitments that let players check a board wasn't changed after dealing.
+//!
+//! When a match is dealt, the contract publishes the SHA-256 hash of a salt
+//! drawn for that match followed by the layout, two bytes per card (the shape
+//! and color index) in row-major order. Once the match is over the salt and
+//! layout are revealed, and `verify_commitment` recomputes the hash from them.
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::state::{Card, Color, Shape};
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CardFace {
+    pub shape: Shape,
+    pub color: Color,
+}
+
+pub fn layout(cards: &[Vec<Card>]) -> Vec<Vec<CardFace>> {
+    cards
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|card| CardFace {
+                    shape: card.shape.clone(),
+                    color: card.color.clone(),
+                })
+                .collect()
+        })
+        .collect()
+}
+
+pub fn commitment(salt: &[u8], layout: &[Vec<CardFace>]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(salt);
+    for face in layout.iter().flatten() {
+        hasher.update([face.shape.clone() as u8, face.color.clone() as u8]);
+    }
+    hasher.finalize().into()
+}
+
+pub fn verify_commitment(commitment_hash: &[u8], salt: &[u8], layout: &[Vec<CardFace>]) -> bool {
+    commitment(salt, layout) == commitment_hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn board() -> Vec<Vec<CardFace>> {
+        vec![
+            vec![
+                CardFace {
+                    shape: Shape::Circle,
+                    color: Color::Red,
+                },
+                CardFace {
+                    shape: Shape::Oval,
+                    color: Color::Blue,
+                },
+            ],
+            vec![
+                CardFace {
+                    shape: Shape::Oval,
+                    color: Color::Blue,
+                },
+                CardFace {
+                    shape: Shape::Circle,
+                    color: Color::Red,
+                },
+            ],
+        ]
+    }
+
+    #[test]
+    fn verify() {
+        let salt = [7u8; 32];
+        let hash = commitment(&salt, &board());
+        assert!(verify_commitment(&hash, &salt, &board()));
+        assert!(!verify_commitment(&hash, &[8u8; 32], &board()));
+
+        let mut swapped = board();
+        swapped[0].swap(0, 1);
+        assert!(!verify_commitment(&hash, &salt, &swapped));
+    }
+}
@@ -1,18 +1,46 @@
-use core::iter;
 use cosmwasm_std::{
-    to_binary, Api, Binary, CanonicalAddr, Env, Extern, HandleResponse, InitResponse, Querier,
-    StdError, StdResult, Storage,
+    from_binary, log, to_binary, Api, BankMsg, Binary, CanonicalAddr, Coin, CosmosMsg, Env, Extern,
+    HandleResponse, HumanAddr, InitResponse, LogAttribute, Querier, StdError, StdResult, Storage,
+    Uint128,
 };
 use getrandom::register_custom_getrandom;
 use rand::prelude::*;
 use rand_chacha::ChaCha20Rng;
+use secret_toolkit::snip20;
 use uuid::Uuid;
 
-use crate::msg::{CardResponse, HandleMsg, InitMsg, MatchResponse, PlayerResponse, QueryMsg};
+use crate::achievement::{mint_achievement_msg, Achievement, AchievementKind};
+use crate::audit::layout;
+use crate::error::ContractError;
+use crate::msg::{
+    BankrollResponse, CardResponse, ConfigResponse, ContractStatusResponse, DailyResultsResponse,
+    HandleMsg, InitMsg, LeaderboardEntryResponse, LeaderboardResponse, MatchAuditResponse,
+    MatchReplayResponse, MatchResponse, MatchSummary, MatchesResponse, MoveResponse,
+    OpenGameSummary, OpenGamesResponse, PlayerResponse, QueryMsg, QueryWithPermit, ReceiveMsg,
+    TokenScheduleResponse,
+};
+use crate::permit::{self, Permission, Permit};
+use crate::randomness::{Oracle, RandomnessConfig, RandomnessSource};
+use crate::score::compute_score;
 use crate::state::{
-    storage_match, storage_match_read, storage_player, storage_player_read, storage_random,
-    storage_random_read, Card, Match, Player, Random,
+    alias_key, day_of, storage_alias, storage_alias_read, storage_bankroll, storage_bankroll_read,
+    storage_config, storage_config_read, storage_contract_address, storage_contract_address_read,
+    storage_current_day, storage_current_day_read, storage_daily_entry, storage_daily_entry_read,
+    storage_daily_results, storage_daily_results_read, storage_daily_seed, storage_daily_seed_read,
+    storage_leaderboard, storage_leaderboard_read, storage_match, storage_match_read,
+    storage_moves, storage_moves_read, storage_open_game_count, storage_open_game_count_read,
+    storage_open_game_index, storage_open_game_index_read, storage_open_games,
+    storage_open_games_read, storage_player, storage_player_matches, storage_player_matches_read,
+    storage_player_read, storage_prize_pool, storage_prize_pool_read, storage_random,
+    storage_random_read, storage_revoked_permit, storage_token_schedule,
+    storage_token_schedule_read, storage_viewing_key, storage_viewing_key_read, BoardLimits, Card,
+    Config, ContractInfo, ContractStatus, Leaderboard, LeaderboardEntry, Match, MatchStatus, Move,
+    Player, Random, RevealOutcome, TokenEntry, Wager, DAILY_BOARD_SIZE, DAILY_SEED_DOMAIN,
+    DEFAULT_LEADERBOARD_SIZE, DEFAULT_MATCH_TIMEOUT, MATCH_ID_DOMAIN, MATCH_SEED_DOMAIN,
+    MAX_LEADERBOARD_SIZE, MAX_PLAYERS, MIN_PLAYERS, VIEWING_KEY_DOMAIN,
 };
+use crate::viewing_key::ViewingKey;
+use crate::wager::{TokenSchedule, WagerConfig, STAKE_DENOM};
 
 fn fill_with_nothing(_dest: &mut [u8]) -> Result<(), getrandom::Error> {
     Ok(())
@@ -20,16 +48,59 @@ fn fill_with_nothing(_dest: &mut [u8]) -> Result<(), getrandom::Error> {
 
 register_custom_getrandom!(fill_with_nothing);
 
+const DEFAULT_PAGE_LIMIT: u32 = 10;
+const MAX_PAGE_LIMIT: u32 = 50;
+// Pads SNIP-20 messages to a multiple of this size to hide their length.
+const BLOCK_SIZE: usize = 256;
+
 pub fn init<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
     msg: InitMsg,
 ) -> StdResult<InitResponse> {
+    let admin = match msg.admin {
+        Some(admin) => deps.api.canonical_address(&admin)?,
+        None => deps.api.canonical_address(&env.message.sender)?,
+    };
+    let board_limits = msg.board_limits.unwrap_or_default();
+    board_limits.check()?;
+    let config = Config {
+        admin,
+        pending_admin: None,
+        board_limits,
+        leaderboard_size: DEFAULT_LEADERBOARD_SIZE,
+        match_timeout: DEFAULT_MATCH_TIMEOUT,
+        wager: WagerConfig::default(),
+        token: msg.token,
+        nft: None,
+        achievements: Achievement::defaults(),
+        randomness: RandomnessConfig::default(),
+        public_replays: false,
+        status: ContractStatus::Normal,
+    };
+    storage_config(&mut deps.storage).save(&config)?;
+
+    let mut messages = vec![];
+    if let Some(token) = &config.token {
+        messages.push(snip20::register_receive_msg(
+            env.contract_code_hash.clone(),
+            None,
+            BLOCK_SIZE,
+            token.code_hash.clone(),
+            token.address.clone(),
+        )?);
+    }
+
     let mut random = Random::empty();
-    random.input_entropy(msg.entropy, env.message.sender, env.block.height);
+    random.input_entropy(msg.entropy, &env);
     storage_random(&mut deps.storage).save(&random)?;
+    storage_contract_address(&mut deps.storage).save(&env.contract.address)?;
+    storage_current_day(&mut deps.storage).save(&day_of(env.block.time))?;
 
-    Ok(InitResponse::default())
+    Ok(InitResponse {
+        messages,
+        log: vec![],
+    })
 }
 
 pub fn handle<S: Storage, A: Api, Q: Querier>(
@@ -37,18 +108,132 @@ pub fn handle<S: Storage, A: Api, Q: Querier>(
     env: Env,
     msg: HandleMsg,
 ) -> StdResult<HandleResponse> {
+    let config = storage_config_read(&deps.storage).load()?;
+    match config.status {
+        ContractStatus::Normal => {}
+        ContractStatus::StopStartingMatches => {
+            let is_start_msg = matches!(
+                msg,
+                HandleMsg::StartMatch { .. }
+                    | HandleMsg::StartDailyChallenge { .. }
+                    | HandleMsg::CreateChallenge { .. }
+                    | HandleMsg::AcceptChallenge { .. }
+                    | HandleMsg::OpenGame { .. }
+                    | HandleMsg::JoinGame { .. }
+            );
+            if is_start_msg {
+                return Err(ContractError::MatchesStopped.into());
+            }
+        }
+        ContractStatus::StopAll => {
+            let is_admin_msg = matches!(
+                msg,
+                HandleMsg::UpdateConfig { .. }
+                    | HandleMsg::SetContractStatus { .. }
+                    | HandleMsg::ChangeAdmin { .. }
+                    | HandleMsg::AcceptAdmin {}
+                    | HandleMsg::SetTokenSchedule { .. }
+                    | HandleMsg::SetAchievements { .. }
+                    | HandleMsg::SetPublicReplays { .. }
+                    | HandleMsg::FundBankroll {}
+                    | HandleMsg::WithdrawBankroll { .. }
+            );
+            if !is_admin_msg {
+                return Err(ContractError::ContractStopped.into());
+            }
+        }
+    }
+    advance_current_day(&mut deps.storage, env.block.time)?;
+
+    // Only stakes and bankroll funding are accounted for, so funds sent with
+    // any other message would be stuck in the contract.
+    let accepts_funds = matches!(
+        msg,
+        HandleMsg::StartMatch { .. } | HandleMsg::FundBankroll {}
+    );
+    if !accepts_funds && !env.message.sent_funds.is_empty() {
+        return Err(ContractError::UnexpectedFunds.into());
+    }
+
     match msg {
         HandleMsg::StartMatch {
             entropy,
             rows,
             cols,
         } => try_start_match(deps, env, entropy, rows, cols),
+        HandleMsg::StartDailyChallenge { entropy } => try_start_daily_challenge(deps, env, entropy),
+        HandleMsg::AddEntropy { entropy } => try_add_entropy(deps, env, entropy),
+        HandleMsg::DealMatch { entropy, match_id } => try_deal_match(deps, env, entropy, match_id),
+        HandleMsg::CreateChallenge {
+            entropy,
+            opponent,
+            rows,
+            cols,
+        } => try_create_challenge(deps, env, entropy, opponent, rows, cols),
+        HandleMsg::AcceptChallenge { match_id } => try_accept_challenge(deps, env, match_id),
+        HandleMsg::OpenGame {
+            entropy,
+            rows,
+            cols,
+            max_players,
+        } => try_open_game(deps, env, entropy, rows, cols, max_players),
+        HandleMsg::JoinGame { match_id } => try_join_game(deps, env, match_id),
+        HandleMsg::CancelGame { match_id } => try_cancel_game(deps, env, match_id),
         HandleMsg::RevealCard {
             entropy,
             match_id,
             pos,
         } => try_reveal_card(deps, env, entropy, match_id, pos),
+        HandleMsg::AbandonMatch { match_id } => try_abandon_match(deps, env, match_id),
+        HandleMsg::ClaimTimeout { match_id } => try_claim_timeout(deps, env, match_id),
+        HandleMsg::CreateViewingKey { entropy } => try_create_viewing_key(deps, env, entropy),
+        HandleMsg::SetViewingKey { key } => try_set_viewing_key(deps, env, key),
+        HandleMsg::RevokePermit { permit_name } => try_revoke_permit(deps, env, permit_name),
+        HandleMsg::SetAlias { alias } => try_set_alias(deps, env, alias),
+        HandleMsg::UpdateConfig {
+            board_limits,
+            leaderboard_size,
+            match_timeout,
+            wager,
+            randomness,
+        } => try_update_config(
+            deps,
+            env,
+            board_limits,
+            leaderboard_size,
+            match_timeout,
+            wager,
+            randomness,
+        ),
+        HandleMsg::SetTokenSchedule {
+            rows,
+            cols,
+            schedule,
+        } => try_set_token_schedule(deps, env, rows, cols, schedule),
+        HandleMsg::Receive {
+            from, amount, msg, ..
+        } => try_receive(deps, env, from, amount, msg),
+        HandleMsg::SetAchievements { nft, achievements } => {
+            try_set_achievements(deps, env, nft, achievements)
+        }
+        HandleMsg::SetPublicReplays { public } => try_set_public_replays(deps, env, public),
+        HandleMsg::FundBankroll {} => try_fund_bankroll(deps, env),
+        HandleMsg::WithdrawBankroll { amount, recipient } => {
+            try_withdraw_bankroll(deps, env, amount, recipient)
+        }
+        HandleMsg::SetContractStatus { status } => try_set_contract_status(deps, env, status),
+        HandleMsg::ChangeAdmin { address } => try_change_admin(deps, env, address),
+        HandleMsg::AcceptAdmin {} => try_accept_admin(deps, env),
+    }
+}
+
+fn advance_current_day<S: Storage>(storage: &mut S, time: u64) -> StdResult<()> {
+    let day = day_of(time);
+    let current_day = storage_current_day_read(storage).may_load()?;
+    if current_day.is_none_or(|current_day| day > current_day) {
+        storage_current_day(storage).save(&day)?;
     }
+    Ok(())
 }
 
 pub fn try_start_match<S: Storage, A: Api, Q: Querier>(
@@ -60,63 +245,82 @@ pub fn try_start_match<S: Storage, A: Api, Q: Querier>(
 ) -> StdResult<HandleResponse> {
     let sender = deps.api.canonical_address(&env.message.sender)?;
 
-    if rows * cols % 2 == 1 {
-        return Err(StdError::GenericErr {
-            msg: "Invalid rows and cols.".to_string(),
-            backtrace: None,
-        });
-    }
+    let stake = sent_amount(&env)?;
+    let wager = if stake.is_zero() {
+        None
+    } else {
+        let config = storage_config_read(&deps.storage).load()?;
+        config.wager.validate(stake, (rows, cols))?;
+        let liability = config.wager.max_payout(stake);
+        let mut bankroll = storage_bankroll_read(&deps.storage)
+            .may_load()?
+            .unwrap_or_default();
+        bankroll.reserve(stake, liability)?;
+        storage_bankroll(&mut deps.storage).save(&bankroll)?;
+        Some(Wager {
+            stake,
+            liability,
+            payout: None,
+        })
+    };
 
-    storage_random(&mut deps.storage).update(|mut random| {
-        random.input_entropy(entropy, env.message.sender, env.block.height);
-        Ok(random)
-    })?;
-    let random = storage_random_read(&deps.storage).load()?;
-    let mut rng = ChaCha20Rng::from_seed(random.seed);
+    let (match_id, mut game_match) = request_match(
+        deps,
+        env,
+        entropy,
+        (rows, cols),
+        vec![sender.clone()],
+        1,
+        MatchStatus::InProgress,
+    )?;
+    game_match.wager = wager;
+    storage_match(&mut deps.storage).save(match_id.as_bytes(), &game_match)?;
+
+    let mut player = storage_player_read(&deps.storage)
+        .may_load(sender.as_slice())?
+        .unwrap_or_else(|| Player::new(sender.clone()));
+    append_match_history(&mut deps.storage, &mut player, &match_id)?;
+    player.matches_started += 1;
+    storage_player(&mut deps.storage).save(sender.as_slice(), &player)?;
 
-    let cards: Vec<Card> = iter::repeat_with(|| Card {
-        shape: rng.gen(),
-        color: rng.gen(),
-        is_revealed: false,
+    Ok(HandleResponse {
+        data: Some(to_binary(&match_id)?),
+        ..HandleResponse::default()
     })
-    .take((rows * cols / 2) as usize)
-    .collect();
-    let mut cards = [cards.as_slice(), cards.as_slice()].concat();
-    cards.shuffle(&mut rng);
-    let cards = cards
-        .chunks(cols as usize)
-        .map(|row| row.to_vec())
-        .collect();
+}
 
-    let mut match_id_buf = [0u8; 16];
-    rng.fill(&mut match_id_buf);
-    let match_id = Uuid::from_slice(&match_id_buf)
-        .unwrap()
-        .simple()
-        .to_string();
+pub fn try_start_daily_challenge<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    entropy: u64,
+) -> StdResult<HandleResponse> {
+    let sender = deps.api.canonical_address(&env.message.sender)?;
 
-    let game_match = Match {
-        player: sender.clone(),
-        size: (rows, cols),
-        cards,
-        last_reveal: None,
-        attempts: 0,
-    };
+    let day = day_of(env.block.time);
+    let entry = storage_daily_entry_read(&deps.storage, day).may_load(sender.as_slice())?;
+    if entry.is_some() {
+        return Err(ContractError::DailyAlreadyPlayed { day }.into());
+    }
+
+    let (match_id, mut game_match) = request_match(
+        deps,
+        env,
+        entropy,
+        DAILY_BOARD_SIZE,
+        vec![sender.clone()],
+        1,
+        MatchStatus::InProgress,
+    )?;
+    game_match.daily = Some(day);
     storage_match(&mut deps.storage).save(match_id.as_bytes(), &game_match)?;
+    storage_daily_entry(&mut deps.storage, day).save(sender.as_slice(), &match_id)?;
 
-    match storage_player_read(&mut deps.storage).may_load(sender.as_slice())? {
-        Some(mut player) => {
-            player.matches.push(match_id.clone());
-            storage_player(&mut deps.storage).save(sender.as_slice(), &player)?;
-        }
-        None => {
-            let player = Player {
-                address: sender.clone(),
-                matches: vec![match_id.clone()],
-            };
-            storage_player(&mut deps.storage).save(sender.as_slice(), &player)?;
-        }
-    }
+    let mut player = storage_player_read(&deps.storage)
+        .may_load(sender.as_slice())?
+        .unwrap_or_else(|| Player::new(sender.clone()));
+    append_match_history(&mut deps.storage, &mut player, &match_id)?;
+    player.matches_started += 1;
+    storage_player(&mut deps.storage).save(sender.as_slice(), &player)?;
 
     Ok(HandleResponse {
         data: Some(to_binary(&match_id)?),
@@ -124,333 +328,3973 @@ pub fn try_start_match<S: Storage, A: Api, Q: Querier>(
     })
 }
 
-pub fn try_reveal_card<S: Storage, A: Api, Q: Querier>(
+pub fn try_create_challenge<S: Storage, A: Api, Q: Querier>(
     deps: &mut Extern<S, A, Q>,
     env: Env,
     entropy: u64,
-    match_id: String,
-    pos: (u32, u32),
+    opponent: HumanAddr,
+    rows: u32,
+    cols: u32,
 ) -> StdResult<HandleResponse> {
     let sender = deps.api.canonical_address(&env.message.sender)?;
+    let opponent = deps.api.canonical_address(&opponent)?;
+    if opponent == sender {
+        return Err(ContractError::CannotChallengeSelf.into());
+    }
 
-    storage_random(&mut deps.storage).update(|mut random| {
-        random.input_entropy(entropy, env.message.sender, env.block.height);
-        Ok(random)
-    })?;
+    let (match_id, game_match) = request_match(
+        deps,
+        env,
+        entropy,
+        (rows, cols),
+        vec![sender.clone(), opponent],
+        2,
+        MatchStatus::Waiting,
+    )?;
+    storage_match(&mut deps.storage).save(match_id.as_bytes(), &game_match)?;
 
-    let mut game_match = storage_match_read(&deps.storage).load(match_id.as_bytes())?;
-    if sender != game_match.player {
-        return Err(StdError::Unauthorized { backtrace: None });
+    // Both players can find the challenge in their history right away, but it
+    // only counts as started once the opponent accepts.
+    for address in &game_match.players {
+        let mut player = storage_player_read(&deps.storage)
+            .may_load(address.as_slice())?
+            .unwrap_or_else(|| Player::new(address.clone()));
+        append_match_history(&mut deps.storage, &mut player, &match_id)?;
+        storage_player(&mut deps.storage).save(address.as_slice(), &player)?;
     }
 
-    let card = game_match.card_at(pos.0 as usize, pos.1 as usize)?;
-    if card.is_revealed {
-        return Err(StdError::GenericErr {
-            msg: "Card already revealed.".to_string(),
-            backtrace: None,
-        });
+    Ok(HandleResponse {
+        data: Some(to_binary(&match_id)?),
+        ..HandleResponse::default()
+    })
+}
+
+pub fn try_accept_challenge<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    match_id: String,
+) -> StdResult<HandleResponse> {
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+
+    let mut game_match = load_match(&deps.storage, &match_id)?;
+    if game_match.players.get(1) != Some(&sender) {
+        return Err(ContractError::NotYourMatch.into());
+    }
+    if game_match.status != MatchStatus::Waiting || !game_match.is_full() {
+        return Err(ContractError::ChallengeNotPending.into());
     }
 
-    match game_match.last_reveal {
-        Some(last_pos) => {
-            let pos = (pos.0 as usize, pos.1 as usize);
-            let last_pos = (last_pos.0 as usize, last_pos.1 as usize);
-            if game_match.does_match(pos, last_pos)? {
-                game_match.reveal(pos.0, pos.1)?;
-                game_match.reveal(last_pos.0, last_pos.1)?;
-            } else {
-                game_match.attempts += 1;
-            }
-            game_match.last_reveal = None;
+    game_match.last_action_height = env.block.height;
+    begin_match(&mut deps.storage, &match_id, &mut game_match)?;
+
+    Ok(HandleResponse::default())
+}
+
+pub fn try_open_game<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    entropy: u64,
+    rows: u32,
+    cols: u32,
+    max_players: u32,
+) -> StdResult<HandleResponse> {
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+    if !(MIN_PLAYERS..=MAX_PLAYERS).contains(&max_players) {
+        return Err(ContractError::InvalidMaxPlayers {
+            min: MIN_PLAYERS,
+            max: MAX_PLAYERS,
         }
-        None => game_match.last_reveal = Some(pos),
+        .into());
     }
+
+    let (match_id, game_match) = request_match(
+        deps,
+        env,
+        entropy,
+        (rows, cols),
+        vec![sender.clone()],
+        max_players,
+        MatchStatus::Waiting,
+    )?;
     storage_match(&mut deps.storage).save(match_id.as_bytes(), &game_match)?;
 
-    let res = CardResponse {
-        shape: card.shape,
-        color: card.color,
-        pos,
-    };
+    let mut player = storage_player_read(&deps.storage)
+        .may_load(sender.as_slice())?
+        .unwrap_or_else(|| Player::new(sender.clone()));
+    append_match_history(&mut deps.storage, &mut player, &match_id)?;
+    storage_player(&mut deps.storage).save(sender.as_slice(), &player)?;
+
+    add_open_game(&mut deps.storage, &match_id)?;
+
     Ok(HandleResponse {
-        data: Some(to_binary(&res)?),
+        data: Some(to_binary(&match_id)?),
         ..HandleResponse::default()
     })
 }
 
-pub fn query<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    msg: QueryMsg,
-) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::GetPlayer { address } => to_binary(&query_player(deps, address)?),
-        QueryMsg::GetCard { match_id, row, col } => {
-            to_binary(&query_card(deps, match_id, row, col)?)
-        }
-        QueryMsg::GetMatch { match_id } => to_binary(&query_match(deps, match_id)?),
+pub fn try_join_game<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    match_id: String,
+) -> StdResult<HandleResponse> {
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+
+    let mut game_match = load_match(&deps.storage, &match_id)?;
+    if !game_match.is_open() {
+        return Err(ContractError::GameNotOpen.into());
+    }
+    if game_match.has_player(&sender) {
+        return Err(ContractError::AlreadyJoined.into());
     }
-}
 
-fn query_player<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    address: CanonicalAddr,
-) -> StdResult<PlayerResponse> {
-    let player = storage_player_read(&deps.storage).load(address.as_slice())?;
-    Ok(PlayerResponse {
-        matches: player.matches,
-    })
-}
+    game_match.join(sender.clone());
+    game_match.last_action_height = env.block.height;
+    let mut player = storage_player_read(&deps.storage)
+        .may_load(sender.as_slice())?
+        .unwrap_or_else(|| Player::new(sender.clone()));
+    append_match_history(&mut deps.storage, &mut player, &match_id)?;
+    storage_player(&mut deps.storage).save(sender.as_slice(), &player)?;
 
-fn query_card<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
-    match_id: String,
-    row: u32,
-    col: u32,
-) -> StdResult<CardResponse> {
-    let game_match = storage_match_read(&deps.storage).load(match_id.as_bytes())?;
-    let card = game_match.card_at(row as usize, col as usize)?;
-    if !card.is_revealed {
-        return Err(StdError::Unauthorized { backtrace: None });
+    if game_match.is_full() {
+        remove_open_game(&mut deps.storage, &match_id)?;
+        begin_match(&mut deps.storage, &match_id, &mut game_match)?;
+    } else {
+        storage_match(&mut deps.storage).save(match_id.as_bytes(), &game_match)?;
     }
-    Ok(CardResponse {
-        shape: card.shape,
-        color: card.color,
-        pos: (row, col),
-    })
+
+    Ok(HandleResponse::default())
 }
 
-fn query_match<S: Storage, A: Api, Q: Querier>(
-    deps: &Extern<S, A, Q>,
+pub fn try_cancel_game<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
     match_id: String,
-) -> StdResult<MatchResponse> {
-    let game_match = storage_match_read(&deps.storage).load(match_id.as_bytes())?;
-    let cards = game_match
-        .cards
-        .iter()
-        .enumerate()
-        .map(|(row, card_row)| {
-            card_row
-                .iter()
-                .enumerate()
-                .map(|(col, card)| {
-                    if card.is_revealed {
-                        Some(CardResponse {
-                            shape: card.shape.clone(),
-                            color: card.color.clone(),
-                            pos: (row as u32, col as u32),
-                        })
-                    } else {
-                        None
-                    }
-                })
-                .collect()
-        })
-        .collect();
-    Ok(MatchResponse {
-        size: game_match.size,
-        attempts: game_match.attempts,
-        cards,
-    })
+) -> StdResult<HandleResponse> {
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+
+    let mut game_match = load_match(&deps.storage, &match_id)?;
+    if game_match.players.first() != Some(&sender) {
+        return Err(ContractError::NotYourMatch.into());
+    }
+    if !game_match.is_open() {
+        return Err(ContractError::GameNotOpen.into());
+    }
+
+    game_match.status = MatchStatus::Cancelled;
+    storage_match(&mut deps.storage).save(match_id.as_bytes(), &game_match)?;
+    remove_open_game(&mut deps.storage, &match_id)?;
+
+    Ok(HandleResponse::default())
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::state::{Color, Shape};
-    use cosmwasm_std::testing::{mock_dependencies, mock_env};
-    use cosmwasm_std::{from_binary, HumanAddr, StdError};
+fn add_open_game<S: Storage>(storage: &mut S, match_id: &str) -> StdResult<()> {
+    let count = storage_open_game_count_read(storage)
+        .may_load()?
+        .unwrap_or_default();
+    storage_open_games(storage).save(&count.to_be_bytes(), &match_id.to_string())?;
+    storage_open_game_index(storage).save(match_id.as_bytes(), &count)?;
+    storage_open_game_count(storage).save(&(count + 1))
+}
 
-    #[test]
-    fn initialization() {
-        let mut deps = mock_dependencies(20, &[]);
+// Moves the last open game into the removed game's position.
+fn remove_open_game<S: Storage>(storage: &mut S, match_id: &str) -> StdResult<()> {
+    let index = match storage_open_game_index_read(storage).may_load(match_id.as_bytes())? {
+        Some(index) => index,
+        None => return Ok(()),
+    };
+    let last = storage_open_game_count_read(storage).load()? - 1;
+    if index != last {
+        let last_id = storage_open_games_read(storage).load(&last.to_be_bytes())?;
+        storage_open_games(storage).save(&index.to_be_bytes(), &last_id)?;
+        storage_open_game_index(storage).save(last_id.as_bytes(), &index)?;
+    }
+    storage_open_games(storage).remove(&last.to_be_bytes());
+    storage_open_game_index(storage).remove(match_id.as_bytes());
+    storage_open_game_count(storage).save(&last)
+}
 
-        let mut rng = thread_rng();
-        let msg = InitMsg { entropy: rng.gen() };
-        let env = mock_env("creator", &[]);
+// Starts a waiting match once every seat is taken, counting it as started for
+// all of its players.
+fn begin_match<S: Storage>(
+    storage: &mut S,
+    match_id: &str,
+    game_match: &mut Match,
+) -> StdResult<()> {
+    game_match.status = MatchStatus::InProgress;
+    storage_match(storage).save(match_id.as_bytes(), game_match)?;
 
-        let res = init(&mut deps, env, msg).unwrap();
-        assert_eq!(res.messages.len(), 0);
+    for address in &game_match.players {
+        storage_player(storage).update(address.as_slice(), |player| {
+            let mut player = player.unwrap_or_else(|| Player::new(address.clone()));
+            player.matches_started += 1;
+            Ok(player)
+        })?;
     }
+    Ok(())
+}
 
-    #[test]
-    fn start_match() {
-        let mut deps = mock_dependencies(20, &[]);
+// Validates the board size and sets up a match under a new id. The board
+// itself is only dealt from a later block, see `deal_requested_match`.
+fn request_match<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    entropy: u64,
+    (rows, cols): (u32, u32),
+    players: Vec<CanonicalAddr>,
+    max_players: u32,
+    status: MatchStatus,
+) -> StdResult<(String, Match)> {
+    let config = storage_config_read(&deps.storage).load()?;
+    config.board_limits.validate(rows, cols)?;
 
-        let mut rng = thread_rng();
-        let msg = InitMsg { entropy: rng.gen() };
-        let env = mock_env("creator", &[]);
-        let _res = init(&mut deps, env, msg).unwrap();
+    storage_random(&mut deps.storage).update(|mut random| {
+        random.input_entropy(entropy, &env);
+        Ok(random)
+    })?;
+    let random = storage_random_read(&deps.storage).load()?;
+    let match_id = new_match_id(&mut ChaCha20Rng::from_seed(
+        random.derive(MATCH_ID_DOMAIN, &[]),
+    ));
 
-        let env = mock_env("player", &[]);
-        let msg = HandleMsg::StartMatch {
-            entropy: rng.gen(),
-            rows: 4,
-            cols: 4,
-        };
-        let res = handle(&mut deps, env, msg).unwrap();
-        let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+    let game_match = Match::new(players, max_players, (rows, cols), status, env.block.height);
 
-        let msg = QueryMsg::GetPlayer { address: deps.api.canonical_address(&HumanAddr("player".into())).unwrap() };
-        let res = query(&deps, msg).unwrap();
-        let player: PlayerResponse = from_binary(&res).unwrap();
-        assert_eq!(player.matches, vec![match_id.clone()]);
+    Ok((match_id, game_match))
+}
 
-        let msg = QueryMsg::GetMatch { match_id };
-        let res = query(&deps, msg).unwrap();
-        let game_match: MatchResponse = from_binary(&res).unwrap();
-        assert_eq!(game_match.size, (4, 4));
-        assert_eq!(game_match.attempts, 0);
-        assert_eq!(game_match.cards, iter::repeat(iter::repeat(None).take(4).collect()).take(4).collect::<Vec<Vec<Option<CardResponse>>>>());
+pub fn try_receive<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    from: HumanAddr,
+    amount: Uint128,
+    msg: Option<Binary>,
+) -> StdResult<HandleResponse> {
+    let config = storage_config_read(&deps.storage).load()?;
+    let is_token = config
+        .token
+        .as_ref()
+        .is_some_and(|token| token.address == env.message.sender);
+    if !is_token {
+        return Err(ContractError::UnknownToken.into());
     }
 
-    #[test]
-    fn reveal_card() {
-        let mut deps = mock_dependencies(20, &[]);
-
-        let msg = InitMsg { entropy: 100 };
-        let env = mock_env("creator", &[]);
-        let _res = init(&mut deps, env, msg).unwrap();
+    let msg: ReceiveMsg = from_binary(&msg.ok_or(ContractError::MissingReceiveMsg)?)?;
+    match msg {
+        ReceiveMsg::StartMatch {
+            entropy,
+            rows,
+            cols,
+        } => {
+            // Other token sends don't start a match and stay allowed.
+            if config.status == ContractStatus::StopStartingMatches {
+                return Err(ContractError::MatchesStopped.into());
+            }
+            let schedule = storage_token_schedule_read(&deps.storage)
+                .may_load(&TokenSchedule::key((rows, cols)))?
+                .ok_or(ContractError::NoTokenSchedule { rows, cols })?;
+            if amount != schedule.entry_fee {
+                return Err(ContractError::WrongEntryFee {
+                    expected: schedule.entry_fee,
+                }
+                .into());
+            }
+            add_to_prize_pool(&mut deps.storage, amount)?;
 
-        let env = mock_env("player", &[]);
-        let msg = HandleMsg::StartMatch {
-            entropy: 200,
-            rows: 4,
-            cols: 4,
-        };
-        let res = handle(&mut deps, env, msg).unwrap();
-        let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+            // The token contract relays the send, so the match is started on
+            // behalf of the token owner.
+            let mut env = env;
+            env.message.sender = from;
+            env.message.sent_funds = vec![];
+            let res = try_start_match(deps, env, entropy, rows, cols)?;
 
-        let unauth_env = mock_env("wrong_player", &[]);
-        let msg = HandleMsg::RevealCard {
-            entropy: 300,
-            match_id,
-            pos: (2, 2),
-        };
-        let res = handle(&mut deps, unauth_env, msg.clone());
-        match res {
-            Err(StdError::Unauthorized { .. }) => {}
-            _ => panic!("Must return unauthorized error"),
+            let match_id: String = from_binary(res.data.as_ref().unwrap())?;
+            let mut game_match = load_match(&deps.storage, &match_id)?;
+            game_match.token_entry = Some(TokenEntry {
+                entry_fee: amount,
+                prize: None,
+            });
+            storage_match(&mut deps.storage).save(match_id.as_bytes(), &game_match)?;
+            Ok(res)
         }
-
-        let auth_env = mock_env("player", &[]);
-        let res = handle(&mut deps, auth_env, msg).unwrap();
+        ReceiveMsg::FundPrizePool {} => {
+            add_to_prize_pool(&mut deps.storage, amount)?;
+            Ok(HandleResponse::default())
+        }
+    }
+}
+
+fn add_to_prize_pool<S: Storage>(storage: &mut S, amount: Uint128) -> StdResult<()> {
+    let prize_pool = storage_prize_pool_read(storage)
+        .may_load()?
+        .unwrap_or_default();
+    storage_prize_pool(storage).save(&(prize_pool + amount))
+}
+
+// Reads the native coins sent along with a message, which may only be a single
+// coin of the stake denomination.
+fn sent_amount(env: &Env) -> StdResult<Uint128> {
+    match env.message.sent_funds.as_slice() {
+        [] => Ok(Uint128(0)),
+        [coin] if coin.denom == STAKE_DENOM => Ok(coin.amount),
+        _ => Err(ContractError::InvalidStake {
+            denom: STAKE_DENOM.to_string(),
+        }
+        .into()),
+    }
+}
+
+fn settle_wager<S: Storage>(storage: &mut S, wager: &mut Wager, payout: Uint128) -> StdResult<()> {
+    let mut bankroll = storage_bankroll_read(storage).load()?;
+    bankroll.settle(wager.liability, payout);
+    storage_bankroll(storage).save(&bankroll)?;
+    wager.payout = Some(payout);
+    Ok(())
+}
+
+fn append_match_history<S: Storage>(
+    storage: &mut S,
+    player: &mut Player,
+    match_id: &str,
+) -> StdResult<()> {
+    storage_player_matches(storage, &player.address)
+        .save(&player.match_count.to_be_bytes(), &match_id.to_string())?;
+    player.match_count += 1;
+    Ok(())
+}
+
+pub fn try_add_entropy<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    entropy: u64,
+) -> StdResult<HandleResponse> {
+    storage_random(&mut deps.storage).update(|mut random| {
+        random.input_entropy(entropy, &env);
+        Ok(random)
+    })?;
+
+    Ok(HandleResponse::default())
+}
+
+pub fn try_deal_match<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    entropy: u64,
+    match_id: String,
+) -> StdResult<HandleResponse> {
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+
+    storage_random(&mut deps.storage).update(|mut random| {
+        random.input_entropy(entropy, &env);
+        Ok(random)
+    })?;
+
+    let mut game_match = load_match(&deps.storage, &match_id)?;
+    if !game_match.has_player(&sender) {
+        return Err(ContractError::NotYourMatch.into());
+    }
+    if game_match.is_finished() {
+        return Err(ContractError::MatchFinished.into());
+    }
+    if game_match.is_dealt() {
+        return Err(ContractError::MatchAlreadyDealt.into());
+    }
+    check_daily_open(&env, &game_match)?;
+
+    deal_requested_match(deps, env.block.height, &match_id, &mut game_match)?;
+    game_match.last_action_height = env.block.height;
+    storage_match(&mut deps.storage).save(match_id.as_bytes(), &game_match)?;
+
+    Ok(HandleResponse {
+        messages: vec![],
+        log: vec![commitment_log(&game_match)],
+        data: Some(to_binary(&match_id)?),
+    })
+}
+
+// Leftover daily matches can't be played once their board may be published.
+fn check_daily_open(env: &Env, game_match: &Match) -> StdResult<()> {
+    match game_match.daily {
+        Some(day) if game_match.is_past_daily(day_of(env.block.time)) => {
+            Err(ContractError::DailyOver { day }.into())
+        }
+        _ => Ok(()),
+    }
+}
+
+// Deals the board of a requested match from the current random seed, which
+// has taken in entropy from every transaction since the request.
+fn deal_requested_match<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    height: u64,
+    match_id: &str,
+    game_match: &mut Match,
+) -> StdResult<()> {
+    let seed = draw_seed(deps, MATCH_SEED_DOMAIN, match_id.as_bytes())?;
+    let mut rng = ChaCha20Rng::from_seed(seed);
+    let (rows, cols) = game_match.size;
+    let cards = match game_match.daily {
+        Some(day) => {
+            let daily_seed = daily_seed(deps, day)?;
+            deal_cards(&mut ChaCha20Rng::from_seed(daily_seed), rows, cols)
+        }
+        None => deal_cards(&mut rng, rows, cols),
+    };
+    let mut salt = [0u8; 32];
+    rng.fill(&mut salt);
+    game_match.deal(seed, cards, salt, height)
+}
+
+// The first daily board dealt in a day fixes the seed for all of them. It
+// stays secret so the board can't be known before it's played.
+fn daily_seed<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    day: u64,
+) -> StdResult<[u8; 32]> {
+    if let Some(seed) = storage_daily_seed_read(&deps.storage).may_load(&day.to_be_bytes())? {
+        return Ok(seed);
+    }
+    let seed = draw_seed(deps, DAILY_SEED_DOMAIN, &day.to_be_bytes())?;
+    storage_daily_seed(&mut deps.storage).save(&day.to_be_bytes(), &seed)?;
+    Ok(seed)
+}
+
+// Draws a seed from the randomness source set in the config.
+fn draw_seed<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    domain: &[u8],
+    id: &[u8],
+) -> StdResult<[u8; 32]> {
+    let config = storage_config_read(&deps.storage).load()?;
+    let random = storage_random_read(&deps.storage).load()?;
+    match &config.randomness {
+        RandomnessConfig::Builtin => random.seed(&deps.querier, domain, id),
+        RandomnessConfig::Oracle { contract } => Oracle {
+            contract,
+            random: &random,
+        }
+        .seed(&deps.querier, domain, id),
+    }
+}
+
+// Lets players audit the layout they were dealt once the match is over.
+fn commitment_log(game_match: &Match) -> LogAttribute {
+    log(
+        "commitment",
+        Binary(game_match.commitment.to_vec()).to_base64(),
+    )
+}
+
+fn new_match_id<R: Rng>(rng: &mut R) -> String {
+    let mut match_id_buf = [0u8; 16];
+    rng.fill(&mut match_id_buf);
+    Uuid::from_slice(&match_id_buf)
+        .unwrap()
+        .simple()
+        .to_string()
+}
+
+fn deal_cards<R: Rng>(rng: &mut R, rows: u32, cols: u32) -> Vec<Vec<Card>> {
+    // Sample pairs from the deck without replacement so that every pair on
+    // the board is distinct.
+    let pairs: Vec<Card> = Card::deck()
+        .choose_multiple(rng, (rows * cols / 2) as usize)
+        .cloned()
+        .collect();
+    let mut cards = [pairs.as_slice(), pairs.as_slice()].concat();
+    cards.shuffle(rng);
+    cards
+        .chunks(cols as usize)
+        .map(|row| row.to_vec())
+        .collect()
+}
+
+pub fn try_reveal_card<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    entropy: u64,
+    match_id: String,
+    pos: (u32, u32),
+) -> StdResult<HandleResponse> {
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+
+    storage_random(&mut deps.storage).update(|mut random| {
+        random.input_entropy(entropy, &env);
+        Ok(random)
+    })?;
+
+    let mut game_match = load_match(&deps.storage, &match_id)?;
+    if !game_match.has_player(&sender) {
+        return Err(ContractError::NotYourMatch.into());
+    }
+    if game_match.is_finished() {
+        return Err(ContractError::MatchFinished.into());
+    }
+    if game_match.status == MatchStatus::Waiting {
+        return Err(ContractError::MatchNotStarted.into());
+    }
+    check_daily_open(&env, &game_match)?;
+    if game_match.current_player() != &sender {
+        return Err(ContractError::NotYourTurn.into());
+    }
+
+    let mut log = vec![];
+    if !game_match.is_dealt() {
+        deal_requested_match(deps, env.block.height, &match_id, &mut game_match)?;
+        log.push(commitment_log(&game_match));
+    }
+
+    if game_match.last_reveal == Some(pos) {
+        return Err(ContractError::SamePosition { pos }.into());
+    }
+
+    let card = game_match.card_at(pos.0 as usize, pos.1 as usize)?;
+    if card.is_revealed {
+        return Err(ContractError::CardAlreadyRevealed { pos }.into());
+    }
+
+    let mut player = storage_player_read(&deps.storage).load(sender.as_slice())?;
+    let mut messages = vec![];
+
+    let outcome = match game_match.last_reveal {
+        Some(last_pos) => {
+            let pos = (pos.0 as usize, pos.1 as usize);
+            let last_pos = (last_pos.0 as usize, last_pos.1 as usize);
+            let outcome = if game_match.does_match(pos, last_pos)? {
+                game_match.reveal(pos.0, pos.1)?;
+                game_match.reveal(last_pos.0, last_pos.1)?;
+                game_match.pairs_found += 1;
+                game_match.pairs[game_match.turn as usize] += 1;
+                game_match.streak += 1;
+                game_match.best_streak = game_match.best_streak.max(game_match.streak);
+                player.total_pairs_found += 1;
+                if game_match.all_revealed() {
+                    game_match.status = MatchStatus::Completed;
+                    game_match.completed_at_height = Some(env.block.height);
+                }
+                if game_match.status == MatchStatus::Completed && game_match.is_solo() {
+                    let score = compute_score(
+                        game_match.size,
+                        game_match.pairs_found,
+                        game_match.attempts,
+                        game_match.best_streak,
+                    );
+                    game_match.score = Some(score);
+                    let previous_best = player.best_score(game_match.size);
+                    player.record_completed(game_match.size, score);
+
+                    let config = storage_config_read(&deps.storage).load()?;
+                    messages.extend(award_achievements(
+                        &config,
+                        &mut player,
+                        &env.message.sender,
+                        &match_id,
+                        &game_match,
+                        previous_best,
+                    )?);
+
+                    let entry = LeaderboardEntry {
+                        player: sender.clone(),
+                        match_id: match_id.clone(),
+                        score,
+                        completed_at_height: env.block.height,
+                    };
+                    // Daily boards are shared and known once a day is over,
+                    // so their scores only count toward that day's results.
+                    match game_match.daily {
+                        Some(day) => add_to_daily_results(&mut deps.storage, day, entry)?,
+                        None => add_to_leaderboard(&mut deps.storage, game_match.size, entry)?,
+                    }
+
+                    if let Some(token_entry) = game_match.token_entry.as_mut() {
+                        let prize =
+                            pay_prize(&mut deps.storage, game_match.size, game_match.attempts)?;
+                        token_entry.prize = Some(prize);
+                        if !prize.is_zero() {
+                            let token = config.token.as_ref().ok_or(ContractError::UnknownToken)?;
+                            messages.push(snip20::transfer_msg(
+                                env.message.sender.clone(),
+                                prize,
+                                None,
+                                None,
+                                BLOCK_SIZE,
+                                token.code_hash.clone(),
+                                token.address.clone(),
+                            )?);
+                        }
+                    }
+
+                    if let Some(wager) = game_match.wager.as_mut() {
+                        // Tiers may have changed since the stake was reserved, so
+                        // never pay out more than was set aside for it.
+                        let payout = config
+                            .wager
+                            .payout(wager.stake, game_match.size, game_match.attempts)
+                            .min(wager.liability);
+                        settle_wager(&mut deps.storage, wager, payout)?;
+                        if !payout.is_zero() {
+                            messages.push(CosmosMsg::Bank(BankMsg::Send {
+                                from_address: env.contract.address.clone(),
+                                to_address: env.message.sender.clone(),
+                                amount: vec![Coin {
+                                    denom: STAKE_DENOM.to_string(),
+                                    amount: payout,
+                                }],
+                            }));
+                        }
+                    }
+                }
+                RevealOutcome::Hit
+            } else {
+                game_match.attempts += 1;
+                game_match.streak = 0;
+                game_match.next_turn();
+                RevealOutcome::Miss
+            };
+            game_match.last_reveal = None;
+            outcome
+        }
+        None => {
+            game_match.last_reveal = Some(pos);
+            RevealOutcome::First
+        }
+    };
+    game_match.last_action_height = env.block.height;
+    let reveal = Move {
+        player: sender.clone(),
+        pos,
+        shape: card.shape.clone(),
+        color: card.color.clone(),
+        outcome,
+        height: env.block.height,
+    };
+    storage_moves(&mut deps.storage, &match_id)
+        .save(&game_match.move_count.to_be_bytes(), &reveal)?;
+    game_match.move_count += 1;
+    storage_match(&mut deps.storage).save(match_id.as_bytes(), &game_match)?;
+    storage_player(&mut deps.storage).save(sender.as_slice(), &player)?;
+    if game_match.status == MatchStatus::Completed && !game_match.is_solo() {
+        messages.extend(record_challenge_result(deps, &match_id, &game_match)?);
+    }
+
+    let res = CardResponse {
+        shape: card.shape,
+        color: card.color,
+        pos,
+    };
+    Ok(HandleResponse {
+        messages,
+        log,
+        data: Some(to_binary(&res)?),
+    })
+}
+
+// Takes the prize for a token match out of the prize pool. The schedule may
+// have changed since the match started, and a prize is capped at what is left
+// in the pool, so completing a match never fails for lack of funds.
+fn pay_prize<S: Storage>(storage: &mut S, size: (u32, u32), misses: u32) -> StdResult<Uint128> {
+    let prize = storage_token_schedule_read(storage)
+        .may_load(&TokenSchedule::key(size))?
+        .map_or(Uint128(0), |schedule| schedule.prize(misses));
+    let prize_pool = storage_prize_pool_read(storage)
+        .may_load()?
+        .unwrap_or_default();
+    let prize = prize.min(prize_pool);
+    storage_prize_pool(storage).save(&Uint128(prize_pool.u128() - prize.u128()))?;
+    Ok(prize)
+}
+
+// Counts a head-to-head match as completed for every player who didn't
+// forfeit it, crediting the win to its winner.
+fn record_challenge_result<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    match_id: &str,
+    game_match: &Match,
+) -> StdResult<Vec<CosmosMsg>> {
+    let config = storage_config_read(&deps.storage).load()?;
+    let winner = game_match.winner();
+    let mut messages = vec![];
+    for address in &game_match.players {
+        if !game_match.is_active(address) {
+            continue;
+        }
+        let mut player = storage_player_read(&deps.storage)
+            .may_load(address.as_slice())?
+            .unwrap_or_else(|| Player::new(address.clone()));
+        player.record_challenge_completed(winner == Some(address));
+        let owner = deps.api.human_address(address)?;
+        messages.extend(award_achievements(
+            &config,
+            &mut player,
+            &owner,
+            match_id,
+            game_match,
+            None,
+        )?);
+        storage_player(&mut deps.storage).save(address.as_slice(), &player)?;
+    }
+    Ok(messages)
+}
+
+// Mints the achievements a player earned with a match they just completed,
+// keeping track of the match counts so each is only ever awarded once.
+fn award_achievements(
+    config: &Config,
+    player: &mut Player,
+    owner: &HumanAddr,
+    match_id: &str,
+    game_match: &Match,
+    previous_best: Option<u64>,
+) -> StdResult<Vec<CosmosMsg>> {
+    let nft = match &config.nft {
+        Some(nft) => nft,
+        None => return Ok(vec![]),
+    };
+    let mut messages = vec![];
+    for achievement in &config.achievements {
+        if !achievement.is_earned(game_match, player, previous_best) {
+            continue;
+        }
+        if let AchievementKind::MatchCount { count } = achievement.kind {
+            player.match_count_awards.push(count);
+        }
+        messages.push(mint_achievement_msg(
+            nft,
+            achievement,
+            owner.clone(),
+            match_id,
+            game_match,
+            BLOCK_SIZE,
+        )?);
+    }
+    Ok(messages)
+}
+
+fn add_to_leaderboard<S: Storage>(
+    storage: &mut S,
+    size: (u32, u32),
+    entry: LeaderboardEntry,
+) -> StdResult<()> {
+    let config = storage_config_read(storage).load()?;
+    let key = Leaderboard::key(size);
+    let mut leaderboard = storage_leaderboard_read(storage)
+        .may_load(&key)?
+        .unwrap_or_default();
+    leaderboard.insert(entry, config.leaderboard_size as usize);
+    storage_leaderboard(storage).save(&key, &leaderboard)
+}
+
+fn add_to_daily_results<S: Storage>(
+    storage: &mut S,
+    day: u64,
+    entry: LeaderboardEntry,
+) -> StdResult<()> {
+    let config = storage_config_read(storage).load()?;
+    let mut results = storage_daily_results_read(storage)
+        .may_load(&day.to_be_bytes())?
+        .unwrap_or_default();
+    results.insert(entry, config.leaderboard_size as usize);
+    storage_daily_results(storage).save(&day.to_be_bytes(), &results)
+}
+
+pub fn try_abandon_match<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    match_id: String,
+) -> StdResult<HandleResponse> {
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+
+    let mut game_match = load_match(&deps.storage, &match_id)?;
+    if !game_match.has_player(&sender) {
+        return Err(ContractError::NotYourMatch.into());
+    }
+    if game_match.is_finished() {
+        return Err(ContractError::MatchFinished.into());
+    }
+    if game_match.left.contains(&sender) {
+        return Err(ContractError::AlreadyLeft.into());
+    }
+
+    // Lobby games are left through CancelGame before they fill up.
+    if game_match.is_open() {
+        return Err(ContractError::MatchNotStarted.into());
+    }
+
+    // Leaving a game of more than two players only takes the leaver out, as
+    // long as at least two players are left to play it.
+    if game_match.status == MatchStatus::InProgress && game_match.active_players() > 2 {
+        game_match.left.push(sender.clone());
+        if game_match.current_player() == &sender {
+            game_match.last_reveal = None;
+            game_match.next_turn();
+        }
+        game_match.last_action_height = env.block.height;
+        storage_match(&mut deps.storage).save(match_id.as_bytes(), &game_match)?;
+        storage_player(&mut deps.storage).update(sender.as_slice(), |player| {
+            let mut player = player.unwrap_or_else(|| Player::new(sender.clone()));
+            player.record_abandoned();
+            Ok(player)
+        })?;
+        return Ok(HandleResponse::default());
+    }
+
+    // Withdrawing or declining a challenge that was never accepted doesn't
+    // count against either player.
+    let was_started = game_match.status == MatchStatus::InProgress;
+    game_match.status = MatchStatus::Abandoned;
+    game_match.last_reveal = None;
+    if was_started {
+        game_match.forfeited_by = Some(sender.clone());
+    }
+    if let Some(wager) = game_match.wager.as_mut() {
+        settle_wager(&mut deps.storage, wager, Uint128(0))?;
+    }
+    storage_match(&mut deps.storage).save(match_id.as_bytes(), &game_match)?;
+    if !was_started {
+        return Ok(HandleResponse::default());
+    }
+
+    storage_player(&mut deps.storage).update(sender.as_slice(), |player| {
+        let mut player = player.unwrap_or_else(|| Player::new(sender.clone()));
+        player.record_abandoned();
+        Ok(player)
+    })?;
+    let messages = if game_match.is_solo() {
+        vec![]
+    } else {
+        record_challenge_result(deps, &match_id, &game_match)?
+    };
+
+    Ok(HandleResponse {
+        messages,
+        ..HandleResponse::default()
+    })
+}
+
+pub fn try_claim_timeout<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    match_id: String,
+) -> StdResult<HandleResponse> {
+    let config = storage_config_read(&deps.storage).load()?;
+
+    let mut game_match = load_match(&deps.storage, &match_id)?;
+    if game_match.is_finished() {
+        return Err(ContractError::MatchFinished.into());
+    }
+    let expires_at_height = game_match.expires_at_height(config.match_timeout);
+    if env.block.height < expires_at_height {
+        return Err(ContractError::MatchNotExpired { expires_at_height }.into());
+    }
+
+    let was_started = game_match.status == MatchStatus::InProgress;
+    if game_match.is_open() {
+        remove_open_game(&mut deps.storage, &match_id)?;
+    }
+    game_match.status = MatchStatus::Expired;
+    game_match.last_reveal = None;
+    if was_started {
+        // Whoever stalled the match forfeits it.
+        game_match.forfeited_by = Some(game_match.current_player().clone());
+    }
+    if let Some(wager) = game_match.wager.as_mut() {
+        settle_wager(&mut deps.storage, wager, Uint128(0))?;
+    }
+    storage_match(&mut deps.storage).save(match_id.as_bytes(), &game_match)?;
+    if !was_started {
+        return Ok(HandleResponse::default());
+    }
+
+    let forfeited_by = game_match.current_player();
+    storage_player(&mut deps.storage).update(forfeited_by.as_slice(), |player| {
+        let mut player = player.unwrap_or_else(|| Player::new(forfeited_by.clone()));
+        player.record_expired();
+        Ok(player)
+    })?;
+    let messages = if game_match.is_solo() {
+        vec![]
+    } else {
+        record_challenge_result(deps, &match_id, &game_match)?
+    };
+
+    Ok(HandleResponse {
+        messages,
+        ..HandleResponse::default()
+    })
+}
+
+pub fn try_create_viewing_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    entropy: u64,
+) -> StdResult<HandleResponse> {
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+
+    storage_random(&mut deps.storage).update(|mut random| {
+        random.input_entropy(entropy, &env);
+        Ok(random)
+    })?;
+    let random = storage_random_read(&deps.storage).load()?;
+
+    let key = ViewingKey::new(&random.derive(VIEWING_KEY_DOMAIN, sender.as_slice()));
+    storage_viewing_key(&mut deps.storage).save(sender.as_slice(), &key.to_hashed())?;
+
+    Ok(HandleResponse {
+        data: Some(to_binary(&key.0)?),
+        ..HandleResponse::default()
+    })
+}
+
+pub fn try_set_viewing_key<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    key: String,
+) -> StdResult<HandleResponse> {
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+
+    let key = ViewingKey(key);
+    storage_viewing_key(&mut deps.storage).save(sender.as_slice(), &key.to_hashed())?;
+
+    Ok(HandleResponse::default())
+}
+
+pub fn try_revoke_permit<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    permit_name: String,
+) -> StdResult<HandleResponse> {
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+    storage_revoked_permit(&mut deps.storage, &sender).save(permit_name.as_bytes(), &true)?;
+    Ok(HandleResponse::default())
+}
+
+fn load_match<S: Storage>(storage: &S, match_id: &str) -> StdResult<Match> {
+    storage_match_read(storage)
+        .may_load(match_id.as_bytes())?
+        .ok_or_else(|| {
+            ContractError::MatchNotFound {
+                match_id: match_id.to_string(),
+            }
+            .into()
+        })
+}
+
+pub fn try_set_alias<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    alias: Option<String>,
+) -> StdResult<HandleResponse> {
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+
+    if let Some(alias) = &alias {
+        Player::validate_alias(alias)?;
+        let owner = storage_alias_read(&deps.storage).may_load(&alias_key(alias))?;
+        if owner.is_some() && owner != Some(sender.clone()) {
+            return Err(ContractError::AliasTaken {
+                alias: alias.clone(),
+            }
+            .into());
+        }
+    }
+
+    let mut player = storage_player_read(&deps.storage)
+        .may_load(sender.as_slice())?
+        .unwrap_or_else(|| Player::new(sender.clone()));
+    if let Some(old_alias) = &player.alias {
+        storage_alias(&mut deps.storage).remove(&alias_key(old_alias));
+    }
+    if let Some(alias) = &alias {
+        storage_alias(&mut deps.storage).save(&alias_key(alias), &sender)?;
+    }
+    player.alias = alias;
+    storage_player(&mut deps.storage).save(sender.as_slice(), &player)?;
+
+    Ok(HandleResponse::default())
+}
+
+pub fn try_update_config<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    board_limits: Option<BoardLimits>,
+    leaderboard_size: Option<u32>,
+    match_timeout: Option<u64>,
+    wager: Option<WagerConfig>,
+    randomness: Option<RandomnessConfig>,
+) -> StdResult<HandleResponse> {
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+
+    let mut config = storage_config_read(&deps.storage).load()?;
+    if sender != config.admin {
+        return Err(ContractError::NotAdmin.into());
+    }
+
+    if let Some(board_limits) = board_limits {
+        board_limits.check()?;
+        config.board_limits = board_limits;
+    }
+    if let Some(leaderboard_size) = leaderboard_size {
+        if !(1..=MAX_LEADERBOARD_SIZE).contains(&leaderboard_size) {
+            return Err(ContractError::InvalidLeaderboardSize {
+                max: MAX_LEADERBOARD_SIZE,
+            }
+            .into());
+        }
+        config.leaderboard_size = leaderboard_size;
+    }
+    if let Some(match_timeout) = match_timeout {
+        if match_timeout == 0 {
+            return Err(ContractError::InvalidMatchTimeout.into());
+        }
+        config.match_timeout = match_timeout;
+    }
+    if let Some(wager) = wager {
+        wager.check()?;
+        config.wager = wager;
+    }
+    if let Some(randomness) = randomness {
+        config.randomness = randomness;
+    }
+    storage_config(&mut deps.storage).save(&config)?;
+
+    Ok(HandleResponse::default())
+}
+
+pub fn try_set_token_schedule<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    rows: u32,
+    cols: u32,
+    schedule: Option<TokenSchedule>,
+) -> StdResult<HandleResponse> {
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+
+    let config = storage_config_read(&deps.storage).load()?;
+    if sender != config.admin {
+        return Err(ContractError::NotAdmin.into());
+    }
+
+    let key = TokenSchedule::key((rows, cols));
+    match schedule {
+        Some(schedule) => {
+            config.board_limits.validate(rows, cols)?;
+            schedule.check()?;
+            storage_token_schedule(&mut deps.storage).save(&key, &schedule)?;
+        }
+        None => storage_token_schedule(&mut deps.storage).remove(&key),
+    }
+
+    Ok(HandleResponse::default())
+}
+
+pub fn try_set_achievements<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    nft: Option<ContractInfo>,
+    achievements: Vec<Achievement>,
+) -> StdResult<HandleResponse> {
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+
+    let mut config = storage_config_read(&deps.storage).load()?;
+    if sender != config.admin {
+        return Err(ContractError::NotAdmin.into());
+    }
+
+    Achievement::validate_all(&achievements)?;
+
+    config.nft = nft;
+    config.achievements = achievements;
+    storage_config(&mut deps.storage).save(&config)?;
+
+    Ok(HandleResponse::default())
+}
+
+pub fn try_set_public_replays<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    public: bool,
+) -> StdResult<HandleResponse> {
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+
+    let mut config = storage_config_read(&deps.storage).load()?;
+    if sender != config.admin {
+        return Err(ContractError::NotAdmin.into());
+    }
+
+    config.public_replays = public;
+    storage_config(&mut deps.storage).save(&config)?;
+
+    Ok(HandleResponse::default())
+}
+
+pub fn try_fund_bankroll<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> StdResult<HandleResponse> {
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+
+    let config = storage_config_read(&deps.storage).load()?;
+    if sender != config.admin {
+        return Err(ContractError::NotAdmin.into());
+    }
+
+    let amount = sent_amount(&env)?;
+    let mut bankroll = storage_bankroll_read(&deps.storage)
+        .may_load()?
+        .unwrap_or_default();
+    bankroll.available += amount;
+    storage_bankroll(&mut deps.storage).save(&bankroll)?;
+
+    Ok(HandleResponse::default())
+}
+
+pub fn try_withdraw_bankroll<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    amount: Uint128,
+    recipient: Option<HumanAddr>,
+) -> StdResult<HandleResponse> {
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+
+    let config = storage_config_read(&deps.storage).load()?;
+    if sender != config.admin {
+        return Err(ContractError::NotAdmin.into());
+    }
+
+    // Only the unreserved part of the bankroll can be withdrawn, so running
+    // matches can always be paid out.
+    let mut bankroll = storage_bankroll_read(&deps.storage)
+        .may_load()?
+        .unwrap_or_default();
+    bankroll.available = (bankroll.available - amount)
+        .map_err(|_| StdError::from(ContractError::InsufficientBankroll))?;
+    storage_bankroll(&mut deps.storage).save(&bankroll)?;
+
+    let msg = BankMsg::Send {
+        from_address: env.contract.address,
+        to_address: recipient.unwrap_or(env.message.sender),
+        amount: vec![Coin {
+            denom: STAKE_DENOM.to_string(),
+            amount,
+        }],
+    };
+    Ok(HandleResponse {
+        messages: vec![msg.into()],
+        log: vec![],
+        data: None,
+    })
+}
+
+pub fn try_set_contract_status<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    status: ContractStatus,
+) -> StdResult<HandleResponse> {
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+
+    let mut config = storage_config_read(&deps.storage).load()?;
+    if sender != config.admin {
+        return Err(ContractError::NotAdmin.into());
+    }
+
+    config.status = status;
+    storage_config(&mut deps.storage).save(&config)?;
+
+    Ok(HandleResponse::default())
+}
+
+pub fn try_change_admin<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+    address: HumanAddr,
+) -> StdResult<HandleResponse> {
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+
+    let mut config = storage_config_read(&deps.storage).load()?;
+    if sender != config.admin {
+        return Err(ContractError::NotAdmin.into());
+    }
+
+    config.pending_admin = Some(deps.api.canonical_address(&address)?);
+    storage_config(&mut deps.storage).save(&config)?;
+
+    Ok(HandleResponse::default())
+}
+
+pub fn try_accept_admin<S: Storage, A: Api, Q: Querier>(
+    deps: &mut Extern<S, A, Q>,
+    env: Env,
+) -> StdResult<HandleResponse> {
+    let sender = deps.api.canonical_address(&env.message.sender)?;
+
+    let mut config = storage_config_read(&deps.storage).load()?;
+    if config.pending_admin.as_ref() != Some(&sender) {
+        return Err(ContractError::NotPendingAdmin.into());
+    }
+
+    config.admin = sender;
+    config.pending_admin = None;
+    storage_config(&mut deps.storage).save(&config)?;
+
+    Ok(HandleResponse::default())
+}
+
+pub fn query<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    msg: QueryMsg,
+) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::GetPlayer { address, key } => {
+            let address = authenticate(deps, &address, key)?;
+            to_binary(&query_player(deps, address)?)
+        }
+        QueryMsg::GetCard {
+            address,
+            key,
+            match_id,
+            row,
+            col,
+        } => {
+            let address = authenticate(deps, &address, key)?;
+            to_binary(&query_card(deps, address, match_id, row, col)?)
+        }
+        QueryMsg::GetMatch {
+            address,
+            key,
+            match_id,
+        } => {
+            let address = authenticate(deps, &address, key)?;
+            to_binary(&query_match(deps, address, match_id)?)
+        }
+        QueryMsg::GetMatchAudit {
+            address,
+            key,
+            match_id,
+        } => {
+            let address = authenticate(deps, &address, key)?;
+            to_binary(&query_match_audit(deps, address, match_id)?)
+        }
+        QueryMsg::GetMatchReplay {
+            match_id,
+            start,
+            limit,
+            address,
+            key,
+        } => {
+            // Without a viewing key, only public replays of finished matches
+            // can be read.
+            let viewer = match address {
+                Some(address) => Some(authenticate(deps, &address, key.unwrap_or_default())?),
+                None => None,
+            };
+            to_binary(&query_match_replay(deps, viewer, match_id, start, limit)?)
+        }
+        QueryMsg::GetMatches {
+            address,
+            key,
+            start_after,
+            limit,
+        } => {
+            let address = authenticate(deps, &address, key)?;
+            to_binary(&query_matches(deps, address, start_after, limit)?)
+        }
+        QueryMsg::WithPermit { permit, query } => permit_queries(deps, permit, query),
+        QueryMsg::GetConfig {} => to_binary(&query_config(deps)?),
+        QueryMsg::GetContractStatus {} => to_binary(&query_contract_status(deps)?),
+        QueryMsg::GetBankroll {} => to_binary(&query_bankroll(deps)?),
+        QueryMsg::GetTokenSchedule { rows, cols } => {
+            to_binary(&query_token_schedule(deps, rows, cols)?)
+        }
+        QueryMsg::GetLeaderboard {
+            rows,
+            cols,
+            start,
+            limit,
+        } => to_binary(&query_leaderboard(deps, rows, cols, start, limit)?),
+        QueryMsg::GetDailyResults { day } => to_binary(&query_daily_results(deps, day)?),
+        QueryMsg::ListOpenGames { start, limit } => {
+            to_binary(&query_open_games(deps, start, limit)?)
+        }
+    }
+}
+
+fn permit_queries<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    permit: Permit,
+    query: QueryWithPermit,
+) -> StdResult<Binary> {
+    let contract_address = storage_contract_address_read(&deps.storage).load()?;
+    let address = permit::validate(&deps.storage, &deps.api, &permit, &contract_address)?;
+
+    let required = match query {
+        QueryWithPermit::GetPlayer {} | QueryWithPermit::GetMatches { .. } => Permission::Player,
+        QueryWithPermit::GetCard { .. }
+        | QueryWithPermit::GetMatch { .. }
+        | QueryWithPermit::GetMatchAudit { .. }
+        | QueryWithPermit::GetMatchReplay { .. } => Permission::Match,
+    };
+    if !permit.has_permission(&required) {
+        return Err(ContractError::PermitMissingPermission {
+            permission: required.to_string(),
+        }
+        .into());
+    }
+
+    match query {
+        QueryWithPermit::GetPlayer {} => to_binary(&query_player(deps, address)?),
+        QueryWithPermit::GetCard { match_id, row, col } => {
+            to_binary(&query_card(deps, address, match_id, row, col)?)
+        }
+        QueryWithPermit::GetMatch { match_id } => to_binary(&query_match(deps, address, match_id)?),
+        QueryWithPermit::GetMatchReplay {
+            match_id,
+            start,
+            limit,
+        } => to_binary(&query_match_replay(
+            deps,
+            Some(address),
+            match_id,
+            start,
+            limit,
+        )?),
+        QueryWithPermit::GetMatchAudit { match_id } => {
+            to_binary(&query_match_audit(deps, address, match_id)?)
+        }
+        QueryWithPermit::GetMatches { start_after, limit } => {
+            to_binary(&query_matches(deps, address, start_after, limit)?)
+        }
+    }
+}
+
+fn authenticate<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: &HumanAddr,
+    key: String,
+) -> StdResult<CanonicalAddr> {
+    let address = deps.api.canonical_address(address)?;
+    let hashed = storage_viewing_key_read(&deps.storage).may_load(address.as_slice())?;
+
+    // Compare against a dummy hash when no key is set, so that a missing key
+    // is indistinguishable from a wrong one.
+    let is_valid = ViewingKey(key).check_viewing_key(&hashed.unwrap_or([0u8; 32]));
+    if hashed.is_none() || !is_valid {
+        return Err(ContractError::Unauthorized.into());
+    }
+    Ok(address)
+}
+
+fn query_config<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<ConfigResponse> {
+    let config = storage_config_read(&deps.storage).load()?;
+    let pending_admin = match config.pending_admin {
+        Some(pending_admin) => Some(deps.api.human_address(&pending_admin)?),
+        None => None,
+    };
+    Ok(ConfigResponse {
+        admin: deps.api.human_address(&config.admin)?,
+        pending_admin,
+        board_limits: config.board_limits,
+        leaderboard_size: config.leaderboard_size,
+        match_timeout: config.match_timeout,
+        wager: config.wager,
+        token: config.token,
+        nft: config.nft,
+        achievements: config.achievements,
+        randomness: config.randomness,
+        public_replays: config.public_replays,
+        status: config.status,
+    })
+}
+
+fn query_contract_status<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<ContractStatusResponse> {
+    let config = storage_config_read(&deps.storage).load()?;
+    Ok(ContractStatusResponse {
+        status: config.status,
+    })
+}
+
+fn query_bankroll<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+) -> StdResult<BankrollResponse> {
+    let bankroll = storage_bankroll_read(&deps.storage)
+        .may_load()?
+        .unwrap_or_default();
+    Ok(BankrollResponse {
+        denom: STAKE_DENOM.to_string(),
+        available: bankroll.available,
+        reserved: bankroll.reserved,
+    })
+}
+
+fn query_token_schedule<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    rows: u32,
+    cols: u32,
+) -> StdResult<TokenScheduleResponse> {
+    let schedule =
+        storage_token_schedule_read(&deps.storage).may_load(&TokenSchedule::key((rows, cols)))?;
+    let prize_pool = storage_prize_pool_read(&deps.storage)
+        .may_load()?
+        .unwrap_or_default();
+    Ok(TokenScheduleResponse {
+        size: (rows, cols),
+        schedule,
+        prize_pool,
+    })
+}
+
+fn query_leaderboard<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    rows: u32,
+    cols: u32,
+    start: Option<u32>,
+    limit: Option<u32>,
+) -> StdResult<LeaderboardResponse> {
+    let start = start.unwrap_or(0) as usize;
+    let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(MAX_PAGE_LIMIT) as usize;
+
+    let leaderboard = storage_leaderboard_read(&deps.storage)
+        .may_load(&Leaderboard::key((rows, cols)))?
+        .unwrap_or_default();
+    let entries = leaderboard_entries(deps, leaderboard, start, limit)?;
+
+    Ok(LeaderboardResponse {
+        size: (rows, cols),
+        entries,
+    })
+}
+
+fn query_daily_results<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    day: u64,
+) -> StdResult<DailyResultsResponse> {
+    let results = storage_daily_results_read(&deps.storage)
+        .may_load(&day.to_be_bytes())?
+        .unwrap_or_default();
+    let entries = leaderboard_entries(deps, results, 0, usize::MAX)?;
+
+    Ok(DailyResultsResponse {
+        day,
+        size: DAILY_BOARD_SIZE,
+        entries,
+    })
+}
+
+fn leaderboard_entries<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    leaderboard: Leaderboard,
+    start: usize,
+    limit: usize,
+) -> StdResult<Vec<LeaderboardEntryResponse>> {
+    leaderboard
+        .entries
+        .into_iter()
+        .enumerate()
+        .skip(start)
+        .take(limit)
+        .map(|(rank, entry)| {
+            let alias = storage_player_read(&deps.storage)
+                .may_load(entry.player.as_slice())?
+                .and_then(|player| player.alias);
+            Ok(LeaderboardEntryResponse {
+                rank: rank as u32 + 1,
+                alias,
+                score: entry.score,
+                completed_at_height: entry.completed_at_height,
+            })
+        })
+        .collect()
+}
+
+fn query_open_games<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    start: Option<u32>,
+    limit: Option<u32>,
+) -> StdResult<OpenGamesResponse> {
+    let count = storage_open_game_count_read(&deps.storage)
+        .may_load()?
+        .unwrap_or_default();
+    let start = start.unwrap_or(0).min(count);
+    let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(MAX_PAGE_LIMIT);
+    let end = start.saturating_add(limit).min(count);
+
+    let open_games = storage_open_games_read(&deps.storage);
+    let games = (start..end)
+        .map(|index| {
+            let match_id = open_games.load(&index.to_be_bytes())?;
+            let game_match = load_match(&deps.storage, &match_id)?;
+            Ok(OpenGameSummary {
+                match_id,
+                size: game_match.size,
+                players: game_match.players.len() as u32,
+                max_players: game_match.max_players,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(OpenGamesResponse { games })
+}
+
+fn query_player<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: CanonicalAddr,
+) -> StdResult<PlayerResponse> {
+    let player = storage_player_read(&deps.storage)
+        .may_load(address.as_slice())?
+        .ok_or(ContractError::PlayerNotFound)?;
+    Ok(PlayerResponse {
+        match_count: player.match_count,
+        alias: player.alias,
+        matches_started: player.matches_started,
+        matches_completed: player.matches_completed,
+        matches_abandoned: player.matches_abandoned,
+        matches_expired: player.matches_expired,
+        best_scores: player.best_scores,
+        total_pairs_found: player.total_pairs_found,
+        win_streak: player.win_streak,
+    })
+}
+
+fn query_matches<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: CanonicalAddr,
+    start_after: Option<u32>,
+    limit: Option<u32>,
+) -> StdResult<MatchesResponse> {
+    let player = storage_player_read(&deps.storage)
+        .may_load(address.as_slice())?
+        .ok_or(ContractError::PlayerNotFound)?;
+    let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(MAX_PAGE_LIMIT);
+
+    // Newest first, so the page ends right before `start_after`.
+    let end = start_after.map_or(player.match_count, |seq| seq.min(player.match_count));
+    let history = storage_player_matches_read(&deps.storage, &address);
+    let matches = (end.saturating_sub(limit)..end)
+        .rev()
+        .map(|seq| {
+            let match_id = history.load(&seq.to_be_bytes())?;
+            let game_match = load_match(&deps.storage, &match_id)?;
+            Ok(MatchSummary {
+                seq,
+                match_id,
+                size: game_match.size,
+                status: game_match.status,
+                score: game_match.score,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(MatchesResponse { matches })
+}
+
+fn query_card<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: CanonicalAddr,
+    match_id: String,
+    row: u32,
+    col: u32,
+) -> StdResult<CardResponse> {
+    let game_match = load_match(&deps.storage, &match_id)?;
+    if !game_match.has_player(&address) {
+        return Err(ContractError::NotYourMatch.into());
+    }
+    if !game_match.is_dealt() {
+        return Err(ContractError::MatchNotDealt.into());
+    }
+    let card = game_match.card_at(row as usize, col as usize)?;
+    if !card.is_revealed {
+        return Err(ContractError::CardHidden { pos: (row, col) }.into());
+    }
+    Ok(CardResponse {
+        shape: card.shape,
+        color: card.color,
+        pos: (row, col),
+    })
+}
+
+fn query_match<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: CanonicalAddr,
+    match_id: String,
+) -> StdResult<MatchResponse> {
+    let game_match = load_match(&deps.storage, &match_id)?;
+    if !game_match.has_player(&address) {
+        return Err(ContractError::NotYourMatch.into());
+    }
+    let (rows, cols) = game_match.size;
+    let cards = if game_match.is_dealt() {
+        game_match
+            .cards
+            .iter()
+            .enumerate()
+            .map(|(row, card_row)| {
+                card_row
+                    .iter()
+                    .enumerate()
+                    .map(|(col, card)| {
+                        if card.is_revealed {
+                            Some(CardResponse {
+                                shape: card.shape.clone(),
+                                color: card.color.clone(),
+                                pos: (row as u32, col as u32),
+                            })
+                        } else {
+                            None
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    } else {
+        vec![vec![None; cols as usize]; rows as usize]
+    };
+    let players = game_match
+        .players
+        .iter()
+        .map(|player| deps.api.human_address(player))
+        .collect::<StdResult<Vec<_>>>()?;
+    let turn = match game_match.status {
+        MatchStatus::InProgress if !game_match.is_solo() => {
+            Some(deps.api.human_address(game_match.current_player())?)
+        }
+        _ => None,
+    };
+    let winner = match game_match.winner() {
+        Some(winner) => Some(deps.api.human_address(winner)?),
+        None => None,
+    };
+    let forfeited_by = match &game_match.forfeited_by {
+        Some(forfeited_by) => Some(deps.api.human_address(forfeited_by)?),
+        None => None,
+    };
+    let left = game_match
+        .left
+        .iter()
+        .map(|player| deps.api.human_address(player))
+        .collect::<StdResult<Vec<_>>>()?;
+    let expires_at_height = if game_match.is_finished() {
+        None
+    } else {
+        let config = storage_config_read(&deps.storage).load()?;
+        Some(game_match.expires_at_height(config.match_timeout))
+    };
+    let commitment = game_match
+        .is_dealt()
+        .then(|| Binary(game_match.commitment.to_vec()));
+    Ok(MatchResponse {
+        players,
+        pairs: game_match.pairs.clone(),
+        turn,
+        winner,
+        is_draw: game_match.is_draw(),
+        size: game_match.size,
+        attempts: game_match.attempts,
+        cards,
+        score: game_match.score,
+        status: game_match.status,
+        completed_at_height: game_match.completed_at_height,
+        last_action_height: game_match.last_action_height,
+        expires_at_height,
+        forfeited_by,
+        left,
+        stake: game_match.wager.as_ref().map(|wager| wager.stake),
+        payout: game_match.wager.and_then(|wager| wager.payout),
+        entry_fee: game_match
+            .token_entry
+            .as_ref()
+            .map(|token_entry| token_entry.entry_fee),
+        prize: game_match
+            .token_entry
+            .and_then(|token_entry| token_entry.prize),
+        daily: game_match.daily,
+        commitment,
+    })
+}
+
+fn query_match_replay<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    viewer: Option<CanonicalAddr>,
+    match_id: String,
+    start: Option<u32>,
+    limit: Option<u32>,
+) -> StdResult<MatchReplayResponse> {
+    let game_match = load_match(&deps.storage, &match_id)?;
+    match viewer {
+        Some(address) => {
+            if !game_match.has_player(&address) {
+                return Err(ContractError::NotYourMatch.into());
+            }
+        }
+        None => {
+            let config = storage_config_read(&deps.storage).load()?;
+            if !config.public_replays || !game_match.is_finished() {
+                return Err(ContractError::ReplayNotPublic.into());
+            }
+            check_daily_over(&deps.storage, &game_match)?;
+        }
+    }
+    let start = start.unwrap_or(0).min(game_match.move_count);
+    let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT).min(MAX_PAGE_LIMIT);
+    let end = start.saturating_add(limit).min(game_match.move_count);
+
+    // Replays can be public, so players are only named by their aliases.
+    let aliases = game_match
+        .players
+        .iter()
+        .map(|address| {
+            Ok(storage_player_read(&deps.storage)
+                .may_load(address.as_slice())?
+                .and_then(|player| player.alias))
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    let log = storage_moves_read(&deps.storage, &match_id);
+    let moves = (start..end)
+        .map(|seq| {
+            let reveal = log.load(&seq.to_be_bytes())?;
+            let player = game_match
+                .players
+                .iter()
+                .position(|address| address == &reveal.player)
+                .ok_or_else(|| StdError::not_found("player"))?;
+            Ok(MoveResponse {
+                seq,
+                player: player as u32,
+                pos: reveal.pos,
+                shape: reveal.shape,
+                color: reveal.color,
+                outcome: reveal.outcome,
+                height: reveal.height,
+            })
+        })
+        .collect::<StdResult<Vec<_>>>()?;
+
+    Ok(MatchReplayResponse {
+        match_id,
+        size: game_match.size,
+        status: game_match.status,
+        move_count: game_match.move_count,
+        aliases,
+        moves,
+    })
+}
+
+// Every daily match of a day shares one board, so nothing about it may be
+// revealed until the day is over, even if this match ended early. The day
+// only counts as over once a later transaction has happened, and from then
+// on check_daily_open rejects every move on that day's leftover matches.
+fn check_daily_over<S: Storage>(storage: &S, game_match: &Match) -> StdResult<()> {
+    match game_match.daily {
+        Some(day) if !game_match.is_past_daily(storage_current_day_read(storage).load()?) => {
+            Err(ContractError::DailyNotOver { day }.into())
+        }
+        _ => Ok(()),
+    }
+}
+
+fn query_match_audit<S: Storage, A: Api, Q: Querier>(
+    deps: &Extern<S, A, Q>,
+    address: CanonicalAddr,
+    match_id: String,
+) -> StdResult<MatchAuditResponse> {
+    let game_match = load_match(&deps.storage, &match_id)?;
+    if !game_match.has_player(&address) {
+        return Err(ContractError::NotYourMatch.into());
+    }
+    if !game_match.is_finished() {
+        return Err(ContractError::MatchNotFinished.into());
+    }
+    if !game_match.is_dealt() {
+        return Err(ContractError::MatchNotDealt.into());
+    }
+    check_daily_over(&deps.storage, &game_match)?;
+
+    Ok(MatchAuditResponse {
+        match_id,
+        size: game_match.size,
+        commitment: Binary(game_match.commitment.to_vec()),
+        salt: Binary(game_match.salt.to_vec()),
+        layout: layout(&game_match.cards),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::iter;
+    use crate::permit::{PermitParams, PermitSignature, PubKey};
+    use crate::state::{storage_revoked_permit_read, BestScore, Color, ContractInfo, Shape, SECONDS_PER_DAY};
+    use crate::audit::verify_commitment;
+    use crate::randomness::{OracleQueryMsg, OracleRandomResponse};
+    use crate::wager::PrizeTier;
+    use cosmwasm_std::testing::{mock_dependencies, mock_env, MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
+    use cosmwasm_std::{coins, from_binary, from_slice, Empty, HumanAddr, QuerierResult, QueryRequest, StdError, SystemError, WasmMsg, WasmQuery};
+
+    #[test]
+    fn initialization() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let mut rng = thread_rng();
+        let msg = InitMsg { entropy: rng.gen(), admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+
+        let res = init(&mut deps, env, msg).unwrap();
+        assert_eq!(res.messages.len(), 0);
+    }
+
+    #[test]
+    fn start_match() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let mut rng = thread_rng();
+        let msg = InitMsg { entropy: rng.gen(), admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::StartMatch {
+            entropy: rng.gen(),
+            rows: 4,
+            cols: 4,
+        };
+        let res = handle(&mut deps, env, msg).unwrap();
+        let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::SetViewingKey { key: "player_key".into() };
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let msg = QueryMsg::GetPlayer { address: HumanAddr("player".into()), key: "player_key".into() };
+        let res = query(&deps, msg).unwrap();
+        let player: PlayerResponse = from_binary(&res).unwrap();
+        assert_eq!(player.match_count, 1);
+
+        let msg = QueryMsg::GetMatches { address: HumanAddr("player".into()), key: "player_key".into(), start_after: None, limit: None };
+        let res = query(&deps, msg).unwrap();
+        let history: MatchesResponse = from_binary(&res).unwrap();
+        assert_eq!(history.matches, vec![MatchSummary { seq: 0, match_id: match_id.clone(), size: (4, 4), status: MatchStatus::InProgress, score: None }]);
+
+        let msg = QueryMsg::GetMatch { address: HumanAddr("player".into()), key: "player_key".into(), match_id };
+        let res = query(&deps, msg).unwrap();
+        let game_match: MatchResponse = from_binary(&res).unwrap();
+        assert_eq!(game_match.size, (4, 4));
+        assert_eq!(game_match.attempts, 0);
+        assert_eq!(game_match.cards, iter::repeat_n(iter::repeat_n(None, 4).collect(), 4).collect::<Vec<Vec<Option<CardResponse>>>>());
+    }
+
+    #[test]
+    fn reveal_card() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::StartMatch {
+            entropy: 200,
+            rows: 4,
+            cols: 4,
+        };
+        let res = handle(&mut deps, env, msg).unwrap();
+        let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+
+        let unauth_env = later_env("wrong_player");
+        let msg = HandleMsg::RevealCard {
+            entropy: 300,
+            match_id,
+            pos: (2, 2),
+        };
+        let res = handle(&mut deps, unauth_env, msg.clone());
+        assert_eq!(res.unwrap_err(), ContractError::NotYourMatch.into());
+
+        let auth_env = later_env("player");
+        let res = handle(&mut deps, auth_env, msg).unwrap();
+        let card: CardResponse = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!(card.shape, Shape::Octagon);
+        assert_eq!(card.color, Color::Brown);
+        assert_eq!(card.pos, (2, 2));
+    }
+
+    #[test]
+    fn reveal_card_miss() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::SetViewingKey { key: "player_key".into() };
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::StartMatch {
+            entropy: 200,
+            rows: 4,
+            cols: 4,
+        };
+        let res = handle(&mut deps, env, msg).unwrap();
+        let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+
+        let env = later_env("player");
+        let msg = HandleMsg::RevealCard {
+            entropy: 300,
+            match_id: match_id.clone(),
+            pos: (2, 2),
+        };
+        let res = handle(&mut deps, env, msg.clone()).unwrap();
+        let card: CardResponse = from_binary(&res.data.unwrap()).unwrap();
+
+        let env = later_env("player");
+        let msg = HandleMsg::RevealCard {
+            entropy: 400,
+            match_id: match_id.clone(),
+            pos: (1, 1),
+        };
+        let res = handle(&mut deps, env, msg.clone()).unwrap();
+        let card2: CardResponse = from_binary(&res.data.unwrap()).unwrap();
+
+        assert!(!(card.shape == card2.shape && card.color == card2.color), "Cards must not match.");
+
+        let msg = QueryMsg::GetCard { address: HumanAddr("player".into()), key: "player_key".into(), match_id: match_id.clone(), row: 2, col: 2 };
+        let res = query(&deps, msg);
+        assert_eq!(res.unwrap_err(), ContractError::CardHidden { pos: (2, 2) }.into());
+
+        let msg = QueryMsg::GetCard { address: HumanAddr("player".into()), key: "player_key".into(), match_id: match_id.clone(), row: 1, col: 1 };
+        let res = query(&deps, msg);
+        assert_eq!(res.unwrap_err(), ContractError::CardHidden { pos: (1, 1) }.into());
+
+        let msg = QueryMsg::GetMatch { address: HumanAddr("player".into()), key: "player_key".into(), match_id };
+        let res = query(&deps, msg).unwrap();
+        let game_match: MatchResponse = from_binary(&res).unwrap();
+        assert_eq!(game_match.attempts, 1);
+    }
+
+    #[test]
+    fn reveal_card_hit() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::SetViewingKey { key: "player_key".into() };
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::StartMatch {
+            entropy: 200,
+            rows: 4,
+            cols: 4,
+        };
+        let res = handle(&mut deps, env, msg).unwrap();
+        let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+
+        let env = later_env("player");
+        let msg = HandleMsg::RevealCard {
+            entropy: 300,
+            match_id: match_id.clone(),
+            pos: (2, 2),
+        };
+        let res = handle(&mut deps, env, msg.clone()).unwrap();
         let card: CardResponse = from_binary(&res.data.unwrap()).unwrap();
-        assert_eq!(card.shape, Shape::Pentagon);
-        assert_eq!(card.color, Color::Red);
+
+        let env = later_env("player");
+        let msg = HandleMsg::RevealCard {
+            entropy: 400,
+            match_id: match_id.clone(),
+            pos: (1, 3),
+        };
+        let res = handle(&mut deps, env, msg.clone()).unwrap();
+        let card2: CardResponse = from_binary(&res.data.unwrap()).unwrap();
+
+        assert!(card.shape == card2.shape && card.color == card2.color, "Cards must match.");
+
+        let msg = QueryMsg::GetCard { address: HumanAddr("player".into()), key: "player_key".into(), match_id: match_id.clone(), row: 2, col: 2 };
+        let res = query(&deps, msg).unwrap();
+        let card: CardResponse = from_binary(&res).unwrap();
+        assert_eq!(card.shape, Shape::Octagon);
+        assert_eq!(card.color, Color::Gray);
         assert_eq!(card.pos, (2, 2));
+
+        let msg = QueryMsg::GetCard { address: HumanAddr("player".into()), key: "player_key".into(), match_id: match_id.clone(), row: 1, col: 3 };
+        let res = query(&deps, msg).unwrap();
+        let card: CardResponse = from_binary(&res).unwrap();
+        assert_eq!(card.shape, Shape::Octagon);
+        assert_eq!(card.color, Color::Gray);
+        assert_eq!(card.pos, (1, 3));
+
+        let msg = QueryMsg::GetMatch { address: HumanAddr("player".into()), key: "player_key".into(), match_id };
+        let res = query(&deps, msg).unwrap();
+        let game_match: MatchResponse = from_binary(&res).unwrap();
+        assert_eq!(game_match.attempts, 0);
+    }
+
+    #[test]
+    fn viewing_key() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::StartMatch {
+            entropy: 200,
+            rows: 4,
+            cols: 4,
+        };
+        let res = handle(&mut deps, env, msg).unwrap();
+        let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+
+        let msg = QueryMsg::GetPlayer { address: HumanAddr("player".into()), key: "api_key_".into() };
+        let res = query(&deps, msg);
+        assert_eq!(res.unwrap_err(), ContractError::Unauthorized.into());
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::CreateViewingKey { entropy: 300 };
+        let res = handle(&mut deps, env, msg).unwrap();
+        let key: String = from_binary(&res.data.unwrap()).unwrap();
+        assert!(key.starts_with("api_key_"));
+
+        let msg = QueryMsg::GetPlayer { address: HumanAddr("player".into()), key: key.clone() };
+        let res = query(&deps, msg).unwrap();
+        let player: PlayerResponse = from_binary(&res).unwrap();
+        assert_eq!(player.match_count, 1);
+
+        let msg = QueryMsg::GetPlayer { address: HumanAddr("player".into()), key: "wrong_key".into() };
+        let wrong_key = query(&deps, msg).unwrap_err();
+        let msg = QueryMsg::GetPlayer { address: HumanAddr("nobody".into()), key: "wrong_key".into() };
+        let no_account = query(&deps, msg).unwrap_err();
+        assert_eq!(wrong_key.to_string(), no_account.to_string());
+
+        let env = mock_env("intruder", &[]);
+        let msg = HandleMsg::SetViewingKey { key: "intruder_key".into() };
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let msg = QueryMsg::GetMatch { address: HumanAddr("intruder".into()), key: "intruder_key".into(), match_id };
+        let res = query(&deps, msg);
+        assert_eq!(res.unwrap_err(), ContractError::NotYourMatch.into());
+    }
+
+    fn mock_permit(name: &str, contract: &str, permissions: Vec<Permission>) -> Permit {
+        Permit {
+            params: PermitParams {
+                allowed_tokens: vec![HumanAddr(contract.into())],
+                permit_name: name.into(),
+                chain_id: "cosmos-testnet-14002".into(),
+                permissions,
+            },
+            signature: PermitSignature {
+                pub_key: PubKey {
+                    r#type: "tendermint/PubKeySecp256k1".into(),
+                    // Picked so the signer's address is valid UTF-8, which the mock api needs to turn it into a HumanAddr.
+                    value: Binary([vec![2u8; 29], 152_536u32.to_be_bytes().to_vec()].concat()),
+                },
+                signature: Binary(vec![0u8; 64]),
+            },
+        }
+    }
+
+    #[test]
+    fn query_with_permit() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::StartMatch {
+            entropy: 200,
+            rows: 4,
+            cols: 4,
+        };
+        let res = handle(&mut deps, env, msg).unwrap();
+        let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+
+        // Hand the match over to the permit signer, whose address can't be produced by the mock api.
+        let permit = mock_permit("test", MOCK_CONTRACT_ADDR, vec![Permission::Player, Permission::Match]);
+        let signer = permit::pubkey_to_address(permit.signature.pub_key.value.as_slice());
+        let mut game_match = storage_match_read(&deps.storage).load(match_id.as_bytes()).unwrap();
+        game_match.players = vec![signer.clone()];
+        storage_match(&mut deps.storage).save(match_id.as_bytes(), &game_match).unwrap();
+        let mut player = Player::new(signer.clone());
+        append_match_history(&mut deps.storage, &mut player, &match_id).unwrap();
+        storage_player(&mut deps.storage).save(signer.as_slice(), &player).unwrap();
+
+        let msg = QueryMsg::WithPermit { permit: permit.clone(), query: QueryWithPermit::GetPlayer {} };
+        let res = query(&deps, msg).unwrap();
+        let player: PlayerResponse = from_binary(&res).unwrap();
+        assert_eq!(player.match_count, 1);
+
+        let msg = QueryMsg::WithPermit { permit: permit.clone(), query: QueryWithPermit::GetMatches { start_after: None, limit: None } };
+        let res = query(&deps, msg).unwrap();
+        let history: MatchesResponse = from_binary(&res).unwrap();
+        assert_eq!(history.matches.len(), 1);
+        assert_eq!(history.matches[0].match_id, match_id);
+
+        let msg = QueryMsg::WithPermit { permit: permit.clone(), query: QueryWithPermit::GetMatch { match_id: match_id.clone() } };
+        let res = query(&deps, msg).unwrap();
+        let game_match: MatchResponse = from_binary(&res).unwrap();
+        assert_eq!(game_match.size, (4, 4));
+
+        let other_contract = mock_permit("test", "other_contract", vec![Permission::Player]);
+        let msg = QueryMsg::WithPermit { permit: other_contract, query: QueryWithPermit::GetPlayer {} };
+        assert_eq!(query(&deps, msg).unwrap_err(), ContractError::PermitWrongContract.into());
+
+        let player_only = mock_permit("player_only", MOCK_CONTRACT_ADDR, vec![Permission::Player]);
+        let msg = QueryMsg::WithPermit { permit: player_only, query: QueryWithPermit::GetMatch { match_id: match_id.clone() } };
+        assert_eq!(query(&deps, msg).unwrap_err(), ContractError::PermitMissingPermission { permission: "match".into() }.into());
+
+        // Revocation is keyed by the sender's address, so store it directly for the signer.
+        storage_revoked_permit(&mut deps.storage, &signer).save(b"test", &true).unwrap();
+        let msg = QueryMsg::WithPermit { permit, query: QueryWithPermit::GetPlayer {} };
+        let res = query(&deps, msg);
+        assert_eq!(res.unwrap_err(), ContractError::PermitRevoked { name: "test".into() }.into());
+    }
+
+    #[test]
+    fn revoke_permit() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::RevokePermit { permit_name: "leaked".into() };
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let player = deps.api.canonical_address(&HumanAddr("player".into())).unwrap();
+        let revoked = storage_revoked_permit_read(&deps.storage, &player).may_load(b"leaked").unwrap();
+        assert_eq!(revoked, Some(true));
+        let revoked = storage_revoked_permit_read(&deps.storage, &player).may_load(b"other").unwrap();
+        assert_eq!(revoked, None);
+    }
+
+    #[test]
+    fn complete_match() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::SetViewingKey { key: "player_key".into() };
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::StartMatch {
+            entropy: 200,
+            rows: 2,
+            cols: 2,
+        };
+        let res = handle(&mut deps, env, msg).unwrap();
+        let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+        deal(&mut deps, "player", &match_id);
+
+        let game_match = storage_match_read(&deps.storage).load(match_id.as_bytes()).unwrap();
+        let mut positions: Vec<(u32, u32)> = vec![(0, 0), (0, 1), (1, 0), (1, 1)];
+        let first = game_match.card_at(0, 0).unwrap();
+        positions.sort_by_key(|pos| {
+            let card = game_match.card_at(pos.0 as usize, pos.1 as usize).unwrap();
+            !(card.shape == first.shape && card.color == first.color)
+        });
+
+        for pos in positions {
+            let env = later_env("player");
+            let msg = HandleMsg::RevealCard { entropy: 300, match_id: match_id.clone(), pos };
+            let _res = handle(&mut deps, env, msg).unwrap();
+        }
+
+        let msg = QueryMsg::GetMatch { address: HumanAddr("player".into()), key: "player_key".into(), match_id: match_id.clone() };
+        let res = query(&deps, msg).unwrap();
+        let game_match: MatchResponse = from_binary(&res).unwrap();
+        assert_eq!(game_match.status, MatchStatus::Completed);
+        assert_eq!(game_match.completed_at_height, Some(12_346));
+        assert_eq!(game_match.attempts, 0);
+        assert_eq!(game_match.score, Some(compute_score((2, 2), 2, 0, 2)));
+
+        let env = later_env("player");
+        let msg = HandleMsg::RevealCard { entropy: 400, match_id: match_id.clone(), pos: (0, 0) };
+        assert_eq!(handle(&mut deps, env, msg).unwrap_err(), ContractError::MatchFinished.into());
+
+        let env = later_env("player");
+        let msg = HandleMsg::AbandonMatch { match_id };
+        assert_eq!(handle(&mut deps, env, msg).unwrap_err(), ContractError::MatchFinished.into());
+    }
+
+    #[test]
+    fn abandon_match() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::SetViewingKey { key: "player_key".into() };
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::StartMatch {
+            entropy: 200,
+            rows: 4,
+            cols: 4,
+        };
+        let res = handle(&mut deps, env, msg).unwrap();
+        let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+
+        let env = mock_env("wrong_player", &[]);
+        let msg = HandleMsg::AbandonMatch { match_id: match_id.clone() };
+        let res = handle(&mut deps, env, msg.clone());
+        assert_eq!(res.unwrap_err(), ContractError::NotYourMatch.into());
+
+        let env = mock_env("player", &[]);
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let msg = QueryMsg::GetMatch { address: HumanAddr("player".into()), key: "player_key".into(), match_id: match_id.clone() };
+        let res = query(&deps, msg).unwrap();
+        let game_match: MatchResponse = from_binary(&res).unwrap();
+        assert_eq!(game_match.status, MatchStatus::Abandoned);
+        assert_eq!(game_match.completed_at_height, None);
+        assert_eq!(game_match.score, None);
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::RevealCard { entropy: 300, match_id, pos: (0, 0) };
+        assert_eq!(handle(&mut deps, env, msg).unwrap_err(), ContractError::MatchFinished.into());
+    }
+
+    #[test]
+    fn add_entropy() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let before = storage_random_read(&deps.storage).load().unwrap();
+        let env = mock_env("anyone", &[]);
+        let _res = handle(&mut deps, env, HandleMsg::AddEntropy { entropy: 1 }).unwrap();
+        let after = storage_random_read(&deps.storage).load().unwrap();
+        assert_ne!(after.seed, before.seed);
+        assert_eq!(after.counter, before.counter + 1);
+
+        let mut random = before.clone();
+        random.input_entropy(1, &mock_env("anyone", &[]));
+        assert_eq!(random, after);
+
+        // Block time and the contract address are folded in along with the rest.
+        let mut env = mock_env("anyone", &[]);
+        env.block.time += 1;
+        let mut random = before.clone();
+        random.input_entropy(1, &env);
+        assert_ne!(random.seed, after.seed);
+
+        let mut env = mock_env("anyone", &[]);
+        env.contract.address = HumanAddr("other_contract".into());
+        let mut random = before;
+        random.input_entropy(1, &env);
+        assert_ne!(random.seed, after.seed);
+
+        let seed = after.derive(MATCH_SEED_DOMAIN, b"match");
+        assert_ne!(seed, after.seed);
+        assert_ne!(seed, after.derive(MATCH_SEED_DOMAIN, b"other_match"));
+        assert_ne!(seed, after.derive(DAILY_SEED_DOMAIN, b"match"));
+        assert_ne!(after.derive(b"ab", b"c"), after.derive(b"a", b"bc"));
+    }
+
+    #[test]
+    fn deal_in_later_block() {
+        let setup = || {
+            let mut deps = mock_dependencies(20, &[]);
+            let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+            let env = mock_env("creator", &[]);
+            let _res = init(&mut deps, env, msg).unwrap();
+
+            let env = mock_env("player", &[]);
+            let msg = HandleMsg::StartMatch { entropy: 200, rows: 4, cols: 4 };
+            let res = handle(&mut deps, env, msg).unwrap();
+            let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+            (deps, match_id)
+        };
+
+        // Nothing about the board is decided by the transaction that asked for it.
+        let (mut deps, match_id) = setup();
+        let game_match = storage_match_read(&deps.storage).load(match_id.as_bytes()).unwrap();
+        assert!(!game_match.is_dealt());
+        assert!(game_match.cards.is_empty());
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::DealMatch { entropy: 0, match_id: match_id.clone() };
+        assert_eq!(handle(&mut deps, env, msg.clone()).unwrap_err(), ContractError::DealNotReady { height: 12_346 }.into());
+
+        let env = mock_env("player", &[]);
+        let reveal_msg = HandleMsg::RevealCard { entropy: 0, match_id: match_id.clone(), pos: (0, 0) };
+        assert_eq!(handle(&mut deps, env, reveal_msg).unwrap_err(), ContractError::DealNotReady { height: 12_346 }.into());
+
+        let env = later_env("stranger");
+        assert_eq!(handle(&mut deps, env, msg.clone()).unwrap_err(), ContractError::NotYourMatch.into());
+
+        let _res = deal(&mut deps, "player", &match_id);
+        let dealt = storage_match_read(&deps.storage).load(match_id.as_bytes()).unwrap();
+        assert_eq!(dealt.dealt_at_height, Some(12_346));
+        let random = storage_random_read(&deps.storage).load().unwrap();
+        assert_eq!(dealt.seed, random.derive(MATCH_SEED_DOMAIN, match_id.as_bytes()));
+        assert_eq!(dealt.cards.len(), 4);
+
+        let env = later_env("player");
+        assert_eq!(handle(&mut deps, env, msg).unwrap_err(), ContractError::MatchAlreadyDealt.into());
+
+        // The same request dealt from another block gets another board.
+        let (mut other_deps, other_id) = setup();
+        assert_eq!(other_id, match_id);
+        let mut env = later_env("player");
+        env.block.height += 1;
+        let msg = HandleMsg::DealMatch { entropy: 0, match_id: other_id.clone() };
+        let _res = handle(&mut other_deps, env, msg).unwrap();
+        let other = storage_match_read(&other_deps.storage).load(other_id.as_bytes()).unwrap();
+        assert_ne!(other.cards, dealt.cards);
+    }
+
+    // Answers randomness oracle queries with fixed bytes, and everything else
+    // like the standard mock querier.
+    struct OracleQuerier {
+        random: Binary,
+        base: MockQuerier,
+    }
+
+    impl Querier for OracleQuerier {
+        fn raw_query(&self, bin_request: &[u8]) -> QuerierResult {
+            let request: QueryRequest<Empty> = match from_slice(bin_request) {
+                Ok(request) => request,
+                Err(e) => return Err(SystemError::InvalidRequest { error: e.to_string(), request: bin_request.into() }),
+            };
+            match request {
+                QueryRequest::Wasm(WasmQuery::Smart { contract_addr, callback_code_hash, msg }) if contract_addr == HumanAddr("oracle".into()) => {
+                    assert_eq!(callback_code_hash, "oracle_hash");
+                    let OracleQueryMsg::GetRandom { .. } = from_binary(&msg).unwrap();
+                    Ok(to_binary(&OracleRandomResponse { random: self.random.clone() }))
+                }
+                _ => self.base.raw_query(bin_request),
+            }
+        }
+    }
+
+    #[test]
+    fn oracle_randomness() {
+        let oracle = ContractInfo { address: HumanAddr("oracle".into()), code_hash: "oracle_hash".into() };
+        let setup = |random: Vec<u8>| {
+            let mut deps = Extern {
+                storage: MockStorage::default(),
+                api: MockApi::new(20),
+                querier: OracleQuerier { random: Binary(random), base: MockQuerier::new(&[]) },
+            };
+            let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+            let env = mock_env("creator", &[]);
+            let _res = init(&mut deps, env, msg).unwrap();
+
+            let env = mock_env("creator", &[]);
+            let randomness = RandomnessConfig::Oracle { contract: oracle.clone() };
+            let msg = HandleMsg::UpdateConfig { board_limits: None, leaderboard_size: None, match_timeout: None, wager: None, randomness: Some(randomness) };
+            let _res = handle(&mut deps, env, msg).unwrap();
+
+            let env = mock_env("player", &[]);
+            let msg = HandleMsg::StartMatch { entropy: 200, rows: 4, cols: 4 };
+            let res = handle(&mut deps, env, msg).unwrap();
+            let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+            (deps, match_id)
+        };
+        let deal_msg = |match_id: &str| HandleMsg::DealMatch { entropy: 0, match_id: match_id.into() };
+
+        let (mut deps, match_id) = setup(vec![1; 32]);
+        let res = query(&deps, QueryMsg::GetConfig {}).unwrap();
+        let config: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(config.randomness, RandomnessConfig::Oracle { contract: oracle.clone() });
+
+        let _res = handle(&mut deps, later_env("player"), deal_msg(&match_id)).unwrap();
+        let game_match = storage_match_read(&deps.storage).load(match_id.as_bytes()).unwrap();
+        let random = storage_random_read(&deps.storage).load().unwrap();
+        let source = Oracle { contract: &oracle, random: &random };
+        assert_eq!(game_match.seed, source.seed(&deps.querier, MATCH_SEED_DOMAIN, match_id.as_bytes()).unwrap());
+        assert_ne!(game_match.seed, random.derive(MATCH_SEED_DOMAIN, match_id.as_bytes()));
+
+        // The same match dealt with other oracle output gets another board.
+        let (mut other_deps, other_id) = setup(vec![2; 32]);
+        let _res = handle(&mut other_deps, later_env("player"), deal_msg(&other_id)).unwrap();
+        let other = storage_match_read(&other_deps.storage).load(other_id.as_bytes()).unwrap();
+        assert_ne!(other.cards, game_match.cards);
+
+        let (mut bad_deps, bad_id) = setup(vec![1; 16]);
+        let res = handle(&mut bad_deps, later_env("player"), deal_msg(&bad_id));
+        assert_eq!(res.unwrap_err(), ContractError::InvalidOracleResponse.into());
+    }
+
+    #[test]
+    fn match_audit() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        for player in ["player", "other"] {
+            let env = mock_env(player, &[]);
+            let msg = HandleMsg::SetViewingKey { key: format!("{}_key", player) };
+            let _res = handle(&mut deps, env, msg).unwrap();
+        }
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::StartMatch { entropy: 200, rows: 4, cols: 4 };
+        let res = handle(&mut deps, env, msg).unwrap();
+        let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+
+        let msg = QueryMsg::GetMatch { address: HumanAddr("player".into()), key: "player_key".into(), match_id: match_id.clone() };
+        let res = query(&deps, msg.clone()).unwrap();
+        let game_match: MatchResponse = from_binary(&res).unwrap();
+        assert_eq!(game_match.commitment, None);
+
+        let res = deal(&mut deps, "player", &match_id);
+        assert_eq!(res.log[0].key, "commitment");
+        let commitment = Binary::from_base64(&res.log[0].value).unwrap();
+
+        let res = query(&deps, msg).unwrap();
+        let game_match: MatchResponse = from_binary(&res).unwrap();
+        assert_eq!(game_match.commitment, Some(commitment.clone()));
+
+        let msg = QueryMsg::GetMatchAudit { address: HumanAddr("player".into()), key: "player_key".into(), match_id: match_id.clone() };
+        assert_eq!(query(&deps, msg.clone()).unwrap_err(), ContractError::MatchNotFinished.into());
+
+        solve_match(&mut deps, "player", &match_id);
+
+        let res = query(&deps, msg).unwrap();
+        let audit: MatchAuditResponse = from_binary(&res).unwrap();
+        assert_eq!(audit.commitment, commitment);
+        assert!(verify_commitment(commitment.as_slice(), audit.salt.as_slice(), &audit.layout));
+        let game_match = storage_match_read(&deps.storage).load(match_id.as_bytes()).unwrap();
+        assert_eq!(audit.layout, layout(&game_match.cards));
+
+        let msg = QueryMsg::GetMatchAudit { address: HumanAddr("other".into()), key: "other_key".into(), match_id };
+        assert_eq!(query(&deps, msg).unwrap_err(), ContractError::NotYourMatch.into());
+    }
+
+    #[test]
+    fn daily_deadline() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let day = day_of(mock_env("alice", &[]).block.time);
+        let mut match_ids = vec![];
+        for player in ["alice", "mallory"] {
+            let env = mock_env(player, &[]);
+            let msg = HandleMsg::StartDailyChallenge { entropy: 200 };
+            let res = handle(&mut deps, env, msg).unwrap();
+            let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+            deal(&mut deps, player, &match_id);
+            match_ids.push(match_id);
+        }
+        solve_match(&mut deps, "alice", &match_ids[0]);
+
+        // Once the day is over the board may be published, so Mallory's
+        // leftover match can't be scored with it.
+        let pairs = pair_positions(&deps, &match_ids[1]);
+        let mut env = later_env("mallory");
+        env.block.time += SECONDS_PER_DAY;
+        let msg = HandleMsg::RevealCard { entropy: 0, match_id: match_ids[1].clone(), pos: pairs[0][0] };
+        assert_eq!(handle(&mut deps, env, msg).unwrap_err(), ContractError::DailyOver { day }.into());
+
+        let msg = QueryMsg::GetDailyResults { day };
+        let results: DailyResultsResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert_eq!(results.entries.len(), 1);
+    }
+
+    #[test]
+    fn daily_audit() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::SetViewingKey { key: "player_key".into() };
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let day = day_of(mock_env("player", &[]).block.time);
+        let mut match_ids = vec![];
+        for player in ["player", "other"] {
+            let env = mock_env(player, &[]);
+            let msg = HandleMsg::StartDailyChallenge { entropy: 200 };
+            let res = handle(&mut deps, env, msg).unwrap();
+            let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+            deal(&mut deps, player, &match_id);
+            match_ids.push(match_id);
+        }
+        let match_id = match_ids[0].clone();
+
+        // Abandoning the daily right away must not reveal the shared board.
+        let env = later_env("player");
+        let msg = HandleMsg::AbandonMatch { match_id: match_id.clone() };
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let msg = QueryMsg::GetMatchAudit { address: HumanAddr("player".into()), key: "player_key".into(), match_id };
+        assert_eq!(query(&deps, msg.clone()).unwrap_err(), ContractError::DailyNotOver { day }.into());
+
+        let mut env = mock_env("player", &[]);
+        env.block.time += SECONDS_PER_DAY;
+        let _res = handle(&mut deps, env, HandleMsg::AddEntropy { entropy: 300 }).unwrap();
+
+        let res = query(&deps, msg).unwrap();
+        let audit: MatchAuditResponse = from_binary(&res).unwrap();
+        assert!(verify_commitment(audit.commitment.as_slice(), audit.salt.as_slice(), &audit.layout));
+
+        // With the board public, the other player's unfinished daily can't be played on.
+        let mut env = later_env("other");
+        env.block.time += SECONDS_PER_DAY;
+        let msg = HandleMsg::RevealCard { entropy: 0, match_id: match_ids[1].clone(), pos: (0, 0) };
+        assert_eq!(handle(&mut deps, env, msg).unwrap_err(), ContractError::DailyOver { day }.into());
+    }
+
+    #[test]
+    fn match_replay() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        for player in ["player", "other"] {
+            let env = mock_env(player, &[]);
+            let msg = HandleMsg::SetViewingKey { key: format!("{}_key", player) };
+            let _res = handle(&mut deps, env, msg).unwrap();
+        }
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::StartMatch { entropy: 200, rows: 4, cols: 4 };
+        let res = handle(&mut deps, env, msg).unwrap();
+        let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+        deal(&mut deps, "player", &match_id);
+
+        let pairs = pair_positions(&deps, &match_id);
+        for pos in [pairs[0][0], pairs[0][1], pairs[1][0], pairs[2][0]] {
+            reveal(&mut deps, "player", &match_id, pos).unwrap();
+        }
+
+        let replay = |deps: &Extern<MockStorage, MockApi, MockQuerier>, player: Option<&str>, start: Option<u32>, limit: Option<u32>| {
+            let msg = QueryMsg::GetMatchReplay {
+                match_id: match_id.clone(),
+                start,
+                limit,
+                address: player.map(|player| HumanAddr(player.into())),
+                key: player.map(|player| format!("{}_key", player)),
+            };
+            query(deps, msg).and_then(|res| from_binary::<MatchReplayResponse>(&res))
+        };
+
+        let res = replay(&deps, Some("player"), None, None).unwrap();
+        assert_eq!(res.status, MatchStatus::InProgress);
+        assert_eq!(res.move_count, 4);
+        let outcomes: Vec<RevealOutcome> = res.moves.iter().map(|reveal| reveal.outcome.clone()).collect();
+        assert_eq!(outcomes, vec![RevealOutcome::First, RevealOutcome::Hit, RevealOutcome::First, RevealOutcome::Miss]);
+        let positions: Vec<(u32, u32)> = res.moves.iter().map(|reveal| reveal.pos).collect();
+        assert_eq!(positions, vec![pairs[0][0], pairs[0][1], pairs[1][0], pairs[2][0]]);
+        let game_match = storage_match_read(&deps.storage).load(match_id.as_bytes()).unwrap();
+        let card = game_match.card_at(pairs[1][0].0 as usize, pairs[1][0].1 as usize).unwrap();
+        assert_eq!((&res.moves[2].shape, &res.moves[2].color), (&card.shape, &card.color));
+        assert!(res.moves.iter().all(|reveal| reveal.player == 0 && reveal.height == 12_346));
+        assert_eq!(res.aliases, vec![None]);
+
+        assert_eq!(replay(&deps, Some("other"), None, None).unwrap_err(), ContractError::NotYourMatch.into());
+        assert_eq!(replay(&deps, None, None, None).unwrap_err(), ContractError::ReplayNotPublic.into());
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::SetPublicReplays { public: true };
+        assert_eq!(handle(&mut deps, env, msg).unwrap_err(), ContractError::NotAdmin.into());
+        let env = mock_env("creator", &[]);
+        let msg = HandleMsg::SetPublicReplays { public: true };
+        let _res = handle(&mut deps, env, msg).unwrap();
+        assert_eq!(replay(&deps, None, None, None).unwrap_err(), ContractError::ReplayNotPublic.into());
+
+        for pair in &pairs[1..] {
+            for pos in pair {
+                reveal(&mut deps, "player", &match_id, *pos).unwrap();
+            }
+        }
+
+        let env = mock_env("player", &[]);
+        let _res = handle(&mut deps, env, HandleMsg::SetAlias { alias: Some("ace".into()) }).unwrap();
+
+        let res = replay(&deps, None, None, None).unwrap();
+        assert_eq!(res.status, MatchStatus::Completed);
+        assert_eq!(res.aliases, vec![Some("ace".into())]);
+        assert_eq!(res.move_count, 18);
+        assert_eq!(res.moves.len(), 10);
+        let res = replay(&deps, None, Some(16), Some(5)).unwrap();
+        let seqs: Vec<u32> = res.moves.iter().map(|reveal| reveal.seq).collect();
+        assert_eq!(seqs, vec![16, 17]);
+        assert_eq!(res.moves[1].outcome, RevealOutcome::Hit);
+        assert!(replay(&deps, None, Some(100), None).unwrap().moves.is_empty());
+    }
+
+    #[test]
+    fn daily_replay() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("creator", &[]);
+        let msg = HandleMsg::SetPublicReplays { public: true };
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let day = day_of(mock_env("player", &[]).block.time);
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::StartDailyChallenge { entropy: 200 };
+        let res = handle(&mut deps, env, msg).unwrap();
+        let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+        solve_match(&mut deps, "player", &match_id);
+
+        let msg = QueryMsg::GetMatchReplay { match_id, start: None, limit: None, address: None, key: None };
+        assert_eq!(query(&deps, msg.clone()).unwrap_err(), ContractError::DailyNotOver { day }.into());
+
+        let mut env = mock_env("player", &[]);
+        env.block.time += SECONDS_PER_DAY;
+        let _res = handle(&mut deps, env, HandleMsg::AddEntropy { entropy: 300 }).unwrap();
+
+        let res = query(&deps, msg).unwrap();
+        let replay: MatchReplayResponse = from_binary(&res).unwrap();
+        assert_eq!(replay.move_count, 36);
+    }
+
+    #[test]
+    fn deal_unique_pairs() {
+        let mut rng = ChaCha20Rng::from_seed([7u8; 32]);
+        for rows in 1..=(Card::DECK_SIZE as u32 * 2) {
+            for cols in 1..=(Card::DECK_SIZE as u32 * 2 / rows) {
+                if rows * cols % 2 == 1 {
+                    continue;
+                }
+                let cards = deal_cards(&mut rng, rows, cols);
+                assert_eq!(cards.len(), rows as usize);
+                assert!(cards.iter().all(|row| row.len() == cols as usize));
+
+                let cards: Vec<&Card> = cards.iter().flatten().collect();
+                for card in &cards {
+                    let copies = cards.iter().filter(|other| other.shape == card.shape && other.color == card.color).count();
+                    assert_eq!(copies, 2, "Board {}x{} must contain every card exactly twice.", rows, cols);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn start_match_board_limits() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let board_limits = BoardLimits { min_rows: 2, max_rows: u32::MAX, min_cols: 2, max_cols: u32::MAX, max_cards: 36 };
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: Some(board_limits), token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let cases = [
+            ((0, 4), ContractError::RowsOutOfRange { rows: 0, min: 2, max: u32::MAX }),
+            ((4, 1), ContractError::ColsOutOfRange { cols: 1, min: 2, max: u32::MAX }),
+            ((3, 3), ContractError::InvalidBoardSize { rows: 3, cols: 3 }),
+            ((6, 8), ContractError::TooManyCards { max: 36 }),
+            ((u32::MAX, u32::MAX), ContractError::TooManyCards { max: 36 }),
+        ];
+        for ((rows, cols), err) in cases {
+            let env = mock_env("player", &[]);
+            let msg = HandleMsg::StartMatch { entropy: 200, rows, cols };
+            assert_eq!(handle(&mut deps, env, msg).unwrap_err(), err.into());
+        }
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::StartMatch { entropy: 200, rows: 6, cols: 6 };
+        assert!(handle(&mut deps, env, msg).is_ok());
+    }
+
+    #[test]
+    fn init_invalid_board_limits() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let board_limits = BoardLimits { max_cards: 164, ..BoardLimits::default() };
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: Some(board_limits), token: None };
+        let env = mock_env("creator", &[]);
+        let res = init(&mut deps, env, msg);
+        assert_eq!(res.unwrap_err(), ContractError::InvalidBoardLimits { max_cards: 162 }.into());
+    }
+
+    #[test]
+    fn reveal_same_position() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::StartMatch {
+            entropy: 200,
+            rows: 4,
+            cols: 4,
+        };
+        let res = handle(&mut deps, env, msg).unwrap();
+        let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+        deal(&mut deps, "player", &match_id);
+
+        let env = later_env("player");
+        let msg = HandleMsg::RevealCard { entropy: 300, match_id: match_id.clone(), pos: (2, 2) };
+        let _res = handle(&mut deps, env, msg.clone()).unwrap();
+
+        let env = mock_env("player", &[]);
+        let res = handle(&mut deps, env, msg);
+        assert_eq!(res.unwrap_err(), ContractError::SamePosition { pos: (2, 2) }.into());
+
+        let game_match = storage_match_read(&deps.storage).load(match_id.as_bytes()).unwrap();
+        assert!(!game_match.card_at(2, 2).unwrap().is_revealed);
+        assert_eq!(game_match.last_reveal, Some((2, 2)));
+        assert_eq!(game_match.attempts, 0);
+    }
+
+    // Drives random reveal sequences against a simple model of the game and
+    // checks that the contract accepts and rejects exactly the same moves.
+    #[test]
+    fn reveal_state_machine() {
+        let sizes = [(2, 2), (2, 3), (3, 4), (4, 4), (1, 6)];
+        for seed in 0..50u64 {
+            let mut rng = ChaCha20Rng::seed_from_u64(seed);
+            let mut deps = mock_dependencies(20, &[]);
+
+            let msg = InitMsg { entropy: seed, admin: None, board_limits: None, token: None };
+            let env = mock_env("creator", &[]);
+            let _res = init(&mut deps, env, msg).unwrap();
+
+            let (rows, cols) = sizes[seed as usize % sizes.len()];
+            let env = mock_env("player", &[]);
+            let msg = HandleMsg::StartMatch { entropy: rng.gen(), rows, cols };
+            let res = handle(&mut deps, env, msg).unwrap();
+            let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+            deal(&mut deps, "player", &match_id);
+
+            let layout = storage_match_read(&deps.storage).load(match_id.as_bytes()).unwrap().cards;
+            let mut revealed = vec![vec![false; cols as usize]; rows as usize];
+            let mut last_reveal: Option<(u32, u32)> = None;
+            let mut attempts = 0;
+            let mut pairs_found = 0;
+            let mut streak = 0;
+            let mut best_streak = 0;
+            let mut completed = false;
+
+            for _ in 0..200 {
+                let pos = (rng.gen_range(0..rows + 1), rng.gen_range(0..cols + 1));
+                let env = later_env("player");
+                let msg = HandleMsg::RevealCard { entropy: rng.gen(), match_id: match_id.clone(), pos };
+                let res = handle(&mut deps, env, msg);
+
+                let in_bounds = pos.0 < rows && pos.1 < cols;
+                let valid = !completed
+                    && in_bounds
+                    && last_reveal != Some(pos)
+                    && !revealed[pos.0 as usize][pos.1 as usize];
+                assert_eq!(res.is_ok(), valid, "Seed {}: unexpected result revealing {:?}.", seed, pos);
+                if !valid {
+                    continue;
+                }
+
+                let card = &layout[pos.0 as usize][pos.1 as usize];
+                match last_reveal.take() {
+                    Some(last) => {
+                        let last_card = &layout[last.0 as usize][last.1 as usize];
+                        if card.shape == last_card.shape && card.color == last_card.color {
+                            revealed[pos.0 as usize][pos.1 as usize] = true;
+                            revealed[last.0 as usize][last.1 as usize] = true;
+                            completed = revealed.iter().flatten().all(|is_revealed| *is_revealed);
+                            pairs_found += 1;
+                            streak += 1;
+                            best_streak = best_streak.max(streak);
+                        } else {
+                            attempts += 1;
+                            streak = 0;
+                        }
+                    }
+                    None => last_reveal = Some(pos),
+                }
+
+                let game_match = storage_match_read(&deps.storage).load(match_id.as_bytes()).unwrap();
+                assert_eq!(game_match.attempts, attempts);
+                assert_eq!(game_match.last_reveal, last_reveal);
+                assert_eq!(game_match.is_finished(), completed);
+                assert_eq!(game_match.pairs_found, pairs_found);
+                assert_eq!(game_match.best_streak, best_streak);
+                let score = completed.then(|| compute_score((rows, cols), pairs_found, attempts, best_streak));
+                assert_eq!(game_match.score, score);
+                for (row, card_row) in game_match.cards.iter().enumerate() {
+                    for (col, card) in card_row.iter().enumerate() {
+                        assert_eq!(card.is_revealed, revealed[row][col]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn error_code() {
+        let err: StdError = ContractError::SamePosition { pos: (1, 2) }.into();
+        match err {
+            StdError::GenericErr { msg, .. } => assert_eq!(msg, r#"{"code":"same_position","message":"Card at (1, 2) can't be paired with itself."}"#),
+            _ => panic!("Must return generic error"),
+        }
+    }
+
+    #[test]
+    fn update_config() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let board_limits = BoardLimits { min_rows: 2, max_rows: 6, min_cols: 2, max_cols: 6, max_cards: 36 };
+        let msg = HandleMsg::UpdateConfig { board_limits: Some(board_limits.clone()), leaderboard_size: None, match_timeout: None, wager: None, randomness: None };
+        let env = mock_env("player", &[]);
+        assert_eq!(handle(&mut deps, env, msg.clone()).unwrap_err(), ContractError::NotAdmin.into());
+
+        let env = mock_env("creator", &[]);
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let res = query(&deps, QueryMsg::GetConfig {}).unwrap();
+        let config: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(config.admin, HumanAddr("creator".into()));
+        assert_eq!(config.board_limits, board_limits);
+
+        for leaderboard_size in [0, MAX_LEADERBOARD_SIZE + 1] {
+            let msg = HandleMsg::UpdateConfig { board_limits: None, leaderboard_size: Some(leaderboard_size), match_timeout: None, wager: None, randomness: None };
+            let env = mock_env("creator", &[]);
+            assert_eq!(handle(&mut deps, env, msg).unwrap_err(), ContractError::InvalidLeaderboardSize { max: MAX_LEADERBOARD_SIZE }.into());
+        }
+        for leaderboard_size in [1, MAX_LEADERBOARD_SIZE] {
+            let msg = HandleMsg::UpdateConfig { board_limits: None, leaderboard_size: Some(leaderboard_size), match_timeout: None, wager: None, randomness: None };
+            let env = mock_env("creator", &[]);
+            let _res = handle(&mut deps, env, msg).unwrap();
+            let config: ConfigResponse = from_binary(&query(&deps, QueryMsg::GetConfig {}).unwrap()).unwrap();
+            assert_eq!(config.leaderboard_size, leaderboard_size);
+        }
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::StartMatch { entropy: 200, rows: 8, cols: 8 };
+        assert_eq!(handle(&mut deps, env, msg).unwrap_err(), ContractError::RowsOutOfRange { rows: 8, min: 2, max: 6 }.into());
+    }
+
+    #[test]
+    fn contract_status() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::StartMatch { entropy: 200, rows: 4, cols: 4 };
+        let res = handle(&mut deps, env, msg).unwrap();
+        let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+        deal(&mut deps, "player", &match_id);
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::SetContractStatus { status: ContractStatus::StopAll };
+        assert_eq!(handle(&mut deps, env, msg).unwrap_err(), ContractError::NotAdmin.into());
+
+        let env = mock_env("creator", &[]);
+        let msg = HandleMsg::SetContractStatus { status: ContractStatus::StopStartingMatches };
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let res = query(&deps, QueryMsg::GetContractStatus {}).unwrap();
+        let status: ContractStatusResponse = from_binary(&res).unwrap();
+        assert_eq!(status.status, ContractStatus::StopStartingMatches);
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::StartMatch { entropy: 200, rows: 4, cols: 4 };
+        assert_eq!(handle(&mut deps, env, msg).unwrap_err(), ContractError::MatchesStopped.into());
+
+        let env = later_env("player");
+        let msg = HandleMsg::RevealCard { entropy: 300, match_id: match_id.clone(), pos: (0, 0) };
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("creator", &[]);
+        let msg = HandleMsg::SetContractStatus { status: ContractStatus::StopAll };
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let env = later_env("player");
+        let msg = HandleMsg::RevealCard { entropy: 300, match_id, pos: (0, 1) };
+        assert_eq!(handle(&mut deps, env, msg).unwrap_err(), ContractError::ContractStopped.into());
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::SetViewingKey { key: "player_key".into() };
+        assert_eq!(handle(&mut deps, env, msg).unwrap_err(), ContractError::ContractStopped.into());
+
+        let res = query(&deps, QueryMsg::GetConfig {}).unwrap();
+        let config: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(config.status, ContractStatus::StopAll);
+
+        let env = mock_env("creator", &[]);
+        let msg = HandleMsg::SetContractStatus { status: ContractStatus::Normal };
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::StartMatch { entropy: 200, rows: 4, cols: 4 };
+        let _res = handle(&mut deps, env, msg).unwrap();
+    }
+
+    #[test]
+    fn change_admin() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::ChangeAdmin { address: HumanAddr("new_admin".into()) };
+        assert_eq!(handle(&mut deps, env, msg.clone()).unwrap_err(), ContractError::NotAdmin.into());
+
+        let env = mock_env("creator", &[]);
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let res = query(&deps, QueryMsg::GetConfig {}).unwrap();
+        let config: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(config.admin, HumanAddr("creator".into()));
+        assert_eq!(config.pending_admin, Some(HumanAddr("new_admin".into())));
+
+        let env = mock_env("player", &[]);
+        assert_eq!(handle(&mut deps, env, HandleMsg::AcceptAdmin {}).unwrap_err(), ContractError::NotPendingAdmin.into());
+
+        let env = mock_env("new_admin", &[]);
+        let _res = handle(&mut deps, env, HandleMsg::AcceptAdmin {}).unwrap();
+
+        let res = query(&deps, QueryMsg::GetConfig {}).unwrap();
+        let config: ConfigResponse = from_binary(&res).unwrap();
+        assert_eq!(config.admin, HumanAddr("new_admin".into()));
+        assert_eq!(config.pending_admin, None);
+
+        let env = mock_env("creator", &[]);
+        let msg = HandleMsg::SetContractStatus { status: ContractStatus::StopAll };
+        assert_eq!(handle(&mut deps, env, msg).unwrap_err(), ContractError::NotAdmin.into());
+    }
+
+    // Boards are only dealt from a block after the one that requested them.
+    fn later_env(sender: &str) -> Env {
+        let mut env = mock_env(sender, &[]);
+        env.block.height += 1;
+        env
+    }
+
+    fn deal(deps: &mut Extern<MockStorage, MockApi, MockQuerier>, player: &str, match_id: &str) -> HandleResponse {
+        let env = later_env(player);
+        let msg = HandleMsg::DealMatch { entropy: 0, match_id: match_id.into() };
+        handle(deps, env, msg).unwrap()
+    }
+
+    // Reveals every pair of the match in order without a single miss.
+    fn solve_match(deps: &mut Extern<MockStorage, MockApi, MockQuerier>, player: &str, match_id: &str) {
+        if !storage_match_read(&deps.storage).load(match_id.as_bytes()).unwrap().is_dealt() {
+            deal(deps, player, match_id);
+        }
+        let game_match = storage_match_read(&deps.storage).load(match_id.as_bytes()).unwrap();
+        let (rows, cols) = game_match.size;
+        let mut positions: Vec<(u32, u32)> = (0..rows).flat_map(|row| (0..cols).map(move |col| (row, col))).collect();
+        positions.sort_by_key(|pos| {
+            let card = game_match.card_at(pos.0 as usize, pos.1 as usize).unwrap();
+            Card::deck().iter().position(|other| other.shape == card.shape && other.color == card.color)
+        });
+        for pos in positions {
+            let env = later_env(player);
+            let msg = HandleMsg::RevealCard { entropy: 0, match_id: match_id.into(), pos };
+            let _res = handle(deps, env, msg).unwrap();
+        }
+    }
+
+    #[test]
+    fn leaderboard_ordering() {
+        let entry = |score, completed_at_height| LeaderboardEntry {
+            player: CanonicalAddr(Binary(vec![])),
+            match_id: String::new(),
+            score,
+            completed_at_height,
+        };
+
+        let mut leaderboard = Leaderboard::default();
+        leaderboard.insert(entry(100, 5), 3);
+        leaderboard.insert(entry(300, 7), 3);
+        leaderboard.insert(entry(100, 3), 3);
+        leaderboard.insert(entry(200, 9), 3);
+        leaderboard.insert(entry(50, 1), 3);
+
+        let ranked: Vec<(u64, u64)> = leaderboard.entries.iter().map(|entry| (entry.score, entry.completed_at_height)).collect();
+        assert_eq!(ranked, vec![(300, 7), (200, 9), (100, 3)]);
+    }
+
+    #[test]
+    fn leaderboard() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("alice", &[]);
+        let msg = HandleMsg::SetAlias { alias: Some("alice".into()) };
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("bob", &[]);
+        let msg = HandleMsg::SetAlias { alias: Some("not an alias!".into()) };
+        assert_eq!(handle(&mut deps, env, msg).unwrap_err(), ContractError::InvalidAlias { max_len: 32 }.into());
+
+        for player in ["alice", "bob"] {
+            let env = mock_env(player, &[]);
+            let msg = HandleMsg::StartMatch { entropy: 200, rows: 2, cols: 4 };
+            let res = handle(&mut deps, env, msg).unwrap();
+            let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+            deal(&mut deps, player, &match_id);
+            if player == "bob" {
+                let mut game_match = storage_match_read(&deps.storage).load(match_id.as_bytes()).unwrap();
+                let miss = if game_match.does_match((0, 0), (0, 1)).unwrap() { (0, 2) } else { (0, 1) };
+                for pos in [(0, 0), miss] {
+                    let env = later_env(player);
+                    let msg = HandleMsg::RevealCard { entropy: 0, match_id: match_id.clone(), pos };
+                    let _res = handle(&mut deps, env, msg).unwrap();
+                }
+                game_match = storage_match_read(&deps.storage).load(match_id.as_bytes()).unwrap();
+                assert_eq!(game_match.attempts, 1);
+            }
+            solve_match(&mut deps, player, &match_id);
+        }
+
+        let msg = QueryMsg::GetLeaderboard { rows: 2, cols: 4, start: None, limit: None };
+        let res = query(&deps, msg).unwrap();
+        let leaderboard: LeaderboardResponse = from_binary(&res).unwrap();
+        assert_eq!(leaderboard.size, (2, 4));
+        let entries: Vec<(u32, Option<String>, u64)> = leaderboard.entries.into_iter().map(|entry| (entry.rank, entry.alias, entry.score)).collect();
+        assert_eq!(entries, vec![
+            (1, Some("alice".into()), compute_score((2, 4), 4, 0, 4)),
+            (2, None, compute_score((2, 4), 4, 1, 4)),
+        ]);
+
+        let msg = QueryMsg::GetLeaderboard { rows: 2, cols: 4, start: Some(1), limit: Some(1) };
+        let res = query(&deps, msg).unwrap();
+        let leaderboard: LeaderboardResponse = from_binary(&res).unwrap();
+        assert_eq!(leaderboard.entries.len(), 1);
+        assert_eq!(leaderboard.entries[0].rank, 2);
+
+        let msg = QueryMsg::GetLeaderboard { rows: 4, cols: 4, start: None, limit: None };
+        let res = query(&deps, msg).unwrap();
+        let leaderboard: LeaderboardResponse = from_binary(&res).unwrap();
+        assert!(leaderboard.entries.is_empty());
+    }
+
+    #[test]
+    fn daily_challenge() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let day = day_of(mock_env("alice", &[]).block.time);
+        let mut match_ids = vec![];
+        for (player, entropy) in [("alice", 1), ("bob", 2)] {
+            let env = mock_env(player, &[]);
+            let msg = HandleMsg::StartDailyChallenge { entropy };
+            let res = handle(&mut deps, env, msg).unwrap();
+            let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+            deal(&mut deps, player, &match_id);
+            match_ids.push(match_id);
+        }
+        assert_ne!(match_ids[0], match_ids[1]);
+        let alice_match = storage_match_read(&deps.storage).load(match_ids[0].as_bytes()).unwrap();
+        let bob_match = storage_match_read(&deps.storage).load(match_ids[1].as_bytes()).unwrap();
+        assert_eq!(alice_match.cards, bob_match.cards);
+        assert_eq!(alice_match.size, DAILY_BOARD_SIZE);
+        assert_eq!(alice_match.daily, Some(day));
+
+        let env = mock_env("alice", &[]);
+        let msg = HandleMsg::StartDailyChallenge { entropy: 3 };
+        assert_eq!(handle(&mut deps, env, msg).unwrap_err(), ContractError::DailyAlreadyPlayed { day }.into());
+
+        for (player, match_id) in ["alice", "bob"].iter().zip(&match_ids) {
+            solve_match(&mut deps, player, match_id);
+        }
+
+        let msg = QueryMsg::GetDailyResults { day };
+        let res = query(&deps, msg).unwrap();
+        let results: DailyResultsResponse = from_binary(&res).unwrap();
+        assert_eq!(results.day, day);
+        assert_eq!(results.size, DAILY_BOARD_SIZE);
+        assert_eq!(results.entries.len(), 2);
+
+        // Daily runs stay off the regular leaderboard of their size.
+        let msg = QueryMsg::GetLeaderboard { rows: 6, cols: 6, start: None, limit: None };
+        let res = query(&deps, msg).unwrap();
+        let leaderboard: LeaderboardResponse = from_binary(&res).unwrap();
+        assert!(leaderboard.entries.is_empty());
+
+        let mut env = mock_env("alice", &[]);
+        env.block.time += SECONDS_PER_DAY;
+        let msg = HandleMsg::StartDailyChallenge { entropy: 3 };
+        let res = handle(&mut deps, env, msg).unwrap();
+        let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+        deal(&mut deps, "alice", &match_id);
+        let next_match = storage_match_read(&deps.storage).load(match_id.as_bytes()).unwrap();
+        assert_eq!(next_match.daily, Some(day + 1));
+        assert_ne!(next_match.cards, alice_match.cards);
+
+        let msg = QueryMsg::GetDailyResults { day: day + 1 };
+        let res = query(&deps, msg).unwrap();
+        let results: DailyResultsResponse = from_binary(&res).unwrap();
+        assert!(results.entries.is_empty());
+
+        // The daily board is held to the board limits like any other.
+        let board_limits = BoardLimits { min_rows: 2, max_rows: 4, min_cols: 2, max_cols: 4, max_cards: 16 };
+        let env = mock_env("creator", &[]);
+        let msg = HandleMsg::UpdateConfig { board_limits: Some(board_limits), leaderboard_size: None, match_timeout: None, wager: None, randomness: None };
+        let _res = handle(&mut deps, env, msg).unwrap();
+        let mut env = mock_env("bob", &[]);
+        env.block.time += SECONDS_PER_DAY;
+        let msg = HandleMsg::StartDailyChallenge { entropy: 4 };
+        assert_eq!(handle(&mut deps, env, msg).unwrap_err(), ContractError::RowsOutOfRange { rows: 6, min: 2, max: 4 }.into());
+    }
+
+    #[test]
+    fn player_profile() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("player", &[]);
+        let msg = HandleMsg::SetViewingKey { key: "player_key".into() };
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let mut match_ids = vec![];
+        for (rows, cols) in [(2, 2), (2, 4), (2, 2)] {
+            let env = mock_env("player", &[]);
+            let msg = HandleMsg::StartMatch { entropy: 200, rows, cols };
+            let res = handle(&mut deps, env, msg).unwrap();
+            match_ids.push(from_binary::<String>(&res.data.unwrap()).unwrap());
+        }
+        solve_match(&mut deps, "player", &match_ids[0]);
+        solve_match(&mut deps, "player", &match_ids[1]);
+
+        let msg = QueryMsg::GetPlayer { address: HumanAddr("player".into()), key: "player_key".into() };
+        let res = query(&deps, msg.clone()).unwrap();
+        let player: PlayerResponse = from_binary(&res).unwrap();
+        assert_eq!(player.match_count, 3);
+        assert_eq!(player.matches_started, 3);
+        assert_eq!(player.matches_completed, 2);
+        assert_eq!(player.matches_abandoned, 0);
+        assert_eq!(player.total_pairs_found, 6);
+        assert_eq!(player.win_streak, 2);
+        assert_eq!(player.best_scores, vec![
+            BestScore { size: (2, 2), score: compute_score((2, 2), 2, 0, 2) },
+            BestScore { size: (2, 4), score: compute_score((2, 4), 4, 0, 4) },
+        ]);
+
+        let env = later_env("player");
+        let msg_abandon = HandleMsg::AbandonMatch { match_id: match_ids[2].clone() };
+        let _res = handle(&mut deps, env, msg_abandon).unwrap();
+
+        let res = query(&deps, msg).unwrap();
+        let player: PlayerResponse = from_binary(&res).unwrap();
+        assert_eq!(player.matches_abandoned, 1);
+        assert_eq!(player.win_streak, 0);
     }
 
     #[test]
-    fn reveal_card_miss() {
+    fn unique_alias() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("alice", &[]);
+        let msg = HandleMsg::SetAlias { alias: Some("Ace".into()) };
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("bob", &[]);
+        let msg = HandleMsg::SetAlias { alias: Some("ace".into()) };
+        assert_eq!(handle(&mut deps, env, msg.clone()).unwrap_err(), ContractError::AliasTaken { alias: "ace".into() }.into());
+
+        // Re-setting your own alias is fine.
+        let env = mock_env("alice", &[]);
+        let _res = handle(&mut deps, env, HandleMsg::SetAlias { alias: Some("ACE".into()) }).unwrap();
+
+        let env = mock_env("alice", &[]);
+        let _res = handle(&mut deps, env, HandleMsg::SetAlias { alias: Some("queen".into()) }).unwrap();
+
+        let env = mock_env("bob", &[]);
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let alice = deps.api.canonical_address(&HumanAddr("alice".into())).unwrap();
+        let bob = deps.api.canonical_address(&HumanAddr("bob".into())).unwrap();
+        assert_eq!(storage_alias_read(&deps.storage).may_load(b"ace").unwrap(), Some(bob));
+        assert_eq!(storage_alias_read(&deps.storage).may_load(b"queen").unwrap(), Some(alice));
+
+        let env = mock_env("bob", &[]);
+        let _res = handle(&mut deps, env, HandleMsg::SetAlias { alias: None }).unwrap();
+        assert_eq!(storage_alias_read(&deps.storage).may_load(b"ace").unwrap(), None);
+    }
+
+    #[test]
+    fn match_history() {
         let mut deps = mock_dependencies(20, &[]);
 
-        let msg = InitMsg { entropy: 100 };
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
         let env = mock_env("creator", &[]);
         let _res = init(&mut deps, env, msg).unwrap();
 
         let env = mock_env("player", &[]);
-        let msg = HandleMsg::StartMatch {
-            entropy: 200,
-            rows: 4,
-            cols: 4,
+        let msg = HandleMsg::SetViewingKey { key: "player_key".into() };
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let mut match_ids = vec![];
+        for _ in 0..5 {
+            let env = mock_env("player", &[]);
+            let msg = HandleMsg::StartMatch { entropy: 200, rows: 2, cols: 2 };
+            let res = handle(&mut deps, env, msg).unwrap();
+            match_ids.push(from_binary::<String>(&res.data.unwrap()).unwrap());
+        }
+        solve_match(&mut deps, "player", &match_ids[3]);
+
+        let page = |start_after, limit| {
+            let msg = QueryMsg::GetMatches { address: HumanAddr("player".into()), key: "player_key".into(), start_after, limit };
+            let res = query(&deps, msg).unwrap();
+            let history: MatchesResponse = from_binary(&res).unwrap();
+            history.matches
         };
-        let res = handle(&mut deps, env, msg).unwrap();
+
+        let first = page(None, Some(2));
+        assert_eq!(first.iter().map(|summary| summary.seq).collect::<Vec<_>>(), vec![4, 3]);
+        assert_eq!(first[0].match_id, match_ids[4]);
+        assert_eq!(first[1].status, MatchStatus::Completed);
+        assert_eq!(first[1].score, Some(compute_score((2, 2), 2, 0, 2)));
+        assert_eq!(first[1].size, (2, 2));
+
+        let second = page(Some(3), Some(2));
+        assert_eq!(second.iter().map(|summary| summary.seq).collect::<Vec<_>>(), vec![2, 1]);
+
+        let last = page(Some(1), Some(2));
+        assert_eq!(last.iter().map(|summary| summary.match_id.clone()).collect::<Vec<_>>(), vec![match_ids[0].clone()]);
+
+        assert!(page(Some(0), None).is_empty());
+        assert_eq!(page(Some(100), None).len(), 5);
+    }
+
+    fn pair_positions(deps: &Extern<MockStorage, MockApi, MockQuerier>, match_id: &str) -> Vec<[(u32, u32); 2]> {
+        let game_match = storage_match_read(&deps.storage).load(match_id.as_bytes()).unwrap();
+        let (rows, cols) = game_match.size;
+        let mut pairs: Vec<Vec<(u32, u32)>> = vec![];
+        for pos in (0..rows).flat_map(|row| (0..cols).map(move |col| (row, col))) {
+            let card = game_match.card_at(pos.0 as usize, pos.1 as usize).unwrap();
+            match pairs.iter_mut().find(|pair| game_match.card_at(pair[0].0 as usize, pair[0].1 as usize).unwrap() == card) {
+                Some(pair) => pair.push(pos),
+                None => pairs.push(vec![pos]),
+            }
+        }
+        pairs.into_iter().map(|pair| [pair[0], pair[1]]).collect()
+    }
+
+    fn reveal(deps: &mut Extern<MockStorage, MockApi, MockQuerier>, player: &str, match_id: &str, pos: (u32, u32)) -> StdResult<HandleResponse> {
+        let env = later_env(player);
+        let msg = HandleMsg::RevealCard { entropy: 0, match_id: match_id.into(), pos };
+        handle(deps, env, msg)
+    }
+
+    fn create_challenge(deps: &mut Extern<MockStorage, MockApi, MockQuerier>, rows: u32, cols: u32) -> String {
+        let env = mock_env("alice", &[]);
+        let msg = HandleMsg::CreateChallenge { entropy: 200, opponent: HumanAddr("bob".into()), rows, cols };
+        let res = handle(deps, env, msg).unwrap();
         let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+        deal(deps, "alice", &match_id);
+        match_id
+    }
 
-        let env = mock_env("player", &[]);
-        let msg = HandleMsg::RevealCard {
-            entropy: 300,
-            match_id: match_id.clone(),
-            pos: (2, 2),
-        };
-        let res = handle(&mut deps, env, msg.clone()).unwrap();
-        let card: CardResponse = from_binary(&res.data.unwrap()).unwrap();
+    #[test]
+    fn head_to_head() {
+        let mut deps = mock_dependencies(20, &[]);
 
-        let env = mock_env("player", &[]);
-        let msg = HandleMsg::RevealCard {
-            entropy: 400,
-            match_id: match_id.clone(),
-            pos: (1, 1),
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        for (player, key) in [("alice", "alice_key"), ("bob", "bob_key")] {
+            let env = mock_env(player, &[]);
+            let msg = HandleMsg::SetViewingKey { key: key.into() };
+            let _res = handle(&mut deps, env, msg).unwrap();
+        }
+
+        let env = mock_env("alice", &[]);
+        let msg = HandleMsg::CreateChallenge { entropy: 200, opponent: HumanAddr("alice".into()), rows: 2, cols: 2 };
+        let res = handle(&mut deps, env, msg);
+        assert_eq!(res.unwrap_err(), ContractError::CannotChallengeSelf.into());
+
+        let match_id = create_challenge(&mut deps, 2, 2);
+        let pairs = pair_positions(&deps, &match_id);
+
+        // Bob finds the pending challenge in his history before accepting it.
+        let msg = QueryMsg::GetMatches { address: HumanAddr("bob".into()), key: "bob_key".into(), start_after: None, limit: None };
+        let history: MatchesResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert_eq!(history.matches.len(), 1);
+        assert_eq!((history.matches[0].match_id.clone(), history.matches[0].status.clone()), (match_id.clone(), MatchStatus::Waiting));
+
+        let res = reveal(&mut deps, "alice", &match_id, pairs[0][0]);
+        assert_eq!(res.unwrap_err(), ContractError::MatchNotStarted.into());
+
+        let env = mock_env("alice", &[]);
+        let msg = HandleMsg::AcceptChallenge { match_id: match_id.clone() };
+        let res = handle(&mut deps, env, msg);
+        assert_eq!(res.unwrap_err(), ContractError::NotYourMatch.into());
+
+        let env = mock_env("bob", &[]);
+        let msg = HandleMsg::AcceptChallenge { match_id: match_id.clone() };
+        let _res = handle(&mut deps, env.clone(), msg.clone()).unwrap();
+        let res = handle(&mut deps, env, msg);
+        assert_eq!(res.unwrap_err(), ContractError::ChallengeNotPending.into());
+
+        // Alice goes first and passes the turn to Bob with a miss.
+        let res = reveal(&mut deps, "bob", &match_id, pairs[0][0]);
+        assert_eq!(res.unwrap_err(), ContractError::NotYourTurn.into());
+        let _res = reveal(&mut deps, "alice", &match_id, pairs[0][0]).unwrap();
+        let _res = reveal(&mut deps, "alice", &match_id, pairs[1][0]).unwrap();
+        let res = reveal(&mut deps, "alice", &match_id, pairs[0][0]);
+        assert_eq!(res.unwrap_err(), ContractError::NotYourTurn.into());
+
+        let msg = QueryMsg::GetMatch { address: HumanAddr("alice".into()), key: "alice_key".into(), match_id: match_id.clone() };
+        let res = query(&deps, msg).unwrap();
+        let game_match: MatchResponse = from_binary(&res).unwrap();
+        assert_eq!(game_match.players, vec![HumanAddr("alice".into()), HumanAddr("bob".into())]);
+        assert_eq!(game_match.turn, Some(HumanAddr("bob".into())));
+        assert_eq!(game_match.winner, None);
+
+        // Bob keeps the turn after finding a pair and takes both.
+        for pair in &pairs {
+            let _res = reveal(&mut deps, "bob", &match_id, pair[0]).unwrap();
+            let _res = reveal(&mut deps, "bob", &match_id, pair[1]).unwrap();
+        }
+
+        let msg = QueryMsg::GetMatch { address: HumanAddr("bob".into()), key: "bob_key".into(), match_id: match_id.clone() };
+        let res = query(&deps, msg).unwrap();
+        let game_match: MatchResponse = from_binary(&res).unwrap();
+        assert_eq!(game_match.status, MatchStatus::Completed);
+        assert_eq!(game_match.pairs, vec![0, 2]);
+        assert_eq!(game_match.turn, None);
+        assert_eq!(game_match.winner, Some(HumanAddr("bob".into())));
+        assert!(!game_match.is_draw);
+        assert_eq!(game_match.score, None);
+
+        let player = |address: &str, key: &str| {
+            let msg = QueryMsg::GetPlayer { address: HumanAddr(address.into()), key: key.into() };
+            from_binary::<PlayerResponse>(&query(&deps, msg).unwrap()).unwrap()
         };
-        let res = handle(&mut deps, env, msg.clone()).unwrap();
-        let card2: CardResponse = from_binary(&res.data.unwrap()).unwrap();
+        let alice = player("alice", "alice_key");
+        assert_eq!((alice.match_count, alice.matches_started, alice.matches_completed, alice.win_streak), (1, 1, 1, 0));
+        let bob = player("bob", "bob_key");
+        assert_eq!((bob.match_count, bob.matches_started, bob.matches_completed, bob.win_streak), (1, 1, 1, 1));
+        assert_eq!(bob.total_pairs_found, 2);
+        assert!(bob.best_scores.is_empty());
 
-        assert!(!(card.shape == card2.shape && card.color == card2.color), "Cards must not match.");
+        let msg = QueryMsg::GetLeaderboard { rows: 2, cols: 2, start: None, limit: None };
+        let res = query(&deps, msg).unwrap();
+        let leaderboard: LeaderboardResponse = from_binary(&res).unwrap();
+        assert!(leaderboard.entries.is_empty());
+    }
 
-        let msg = QueryMsg::GetCard { match_id: match_id.clone(), row: 2, col: 2 };
-        let res = query(&deps, msg);
-        match res {
-            Err(StdError::Unauthorized { .. }) => {}
-            _ => panic!("Must return unauthorized error"),
+    #[test]
+    fn head_to_head_forfeit() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        for player in ["alice", "bob"] {
+            let env = mock_env(player, &[]);
+            let msg = HandleMsg::SetViewingKey { key: format!("{}_key", player) };
+            let _res = handle(&mut deps, env, msg).unwrap();
         }
 
-        let msg = QueryMsg::GetCard { match_id: match_id.clone(), row: 1, col: 1 };
-        let res = query(&deps, msg);
-        match res {
-            Err(StdError::Unauthorized { .. }) => {}
-            _ => panic!("Must return unauthorized error"),
+        let match_id = create_challenge(&mut deps, 4, 4);
+        let env = mock_env("bob", &[]);
+        let msg = HandleMsg::AcceptChallenge { match_id: match_id.clone() };
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        // Alice takes a pair and then misses, and Bob leaves while behind.
+        let pairs = pair_positions(&deps, &match_id);
+        for pos in [pairs[0][0], pairs[0][1], pairs[1][0], pairs[2][0]] {
+            let _res = reveal(&mut deps, "alice", &match_id, pos).unwrap();
+        }
+        let env = later_env("bob");
+        let msg = HandleMsg::AbandonMatch { match_id: match_id.clone() };
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let msg = QueryMsg::GetMatch { address: HumanAddr("alice".into()), key: "alice_key".into(), match_id };
+        let game_match: MatchResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert_eq!(game_match.status, MatchStatus::Abandoned);
+        assert_eq!(game_match.forfeited_by, Some(HumanAddr("bob".into())));
+        assert_eq!(game_match.winner, Some(HumanAddr("alice".into())));
+        assert!(!game_match.is_draw);
+
+        let profile = |deps: &Extern<MockStorage, MockApi, MockQuerier>, player: &str| {
+            let msg = QueryMsg::GetPlayer { address: HumanAddr(player.into()), key: format!("{}_key", player) };
+            from_binary::<PlayerResponse>(&query(deps, msg).unwrap()).unwrap()
+        };
+        let alice = profile(&deps, "alice");
+        assert_eq!((alice.matches_completed, alice.matches_abandoned, alice.win_streak), (1, 0, 1));
+        let bob = profile(&deps, "bob");
+        assert_eq!((bob.matches_completed, bob.matches_abandoned, bob.win_streak), (0, 1, 0));
+    }
+
+    #[test]
+    fn head_to_head_draw() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("bob", &[]);
+        let msg = HandleMsg::SetViewingKey { key: "bob_key".into() };
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let match_id = create_challenge(&mut deps, 2, 4);
+        let env = mock_env("bob", &[]);
+        let msg = HandleMsg::AcceptChallenge { match_id: match_id.clone() };
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let pairs = pair_positions(&deps, &match_id);
+        for pair in &pairs[..2] {
+            let _res = reveal(&mut deps, "alice", &match_id, pair[0]).unwrap();
+            let _res = reveal(&mut deps, "alice", &match_id, pair[1]).unwrap();
+        }
+        let _res = reveal(&mut deps, "alice", &match_id, pairs[2][0]).unwrap();
+        let _res = reveal(&mut deps, "alice", &match_id, pairs[3][0]).unwrap();
+        for pair in &pairs[2..] {
+            let _res = reveal(&mut deps, "bob", &match_id, pair[0]).unwrap();
+            let _res = reveal(&mut deps, "bob", &match_id, pair[1]).unwrap();
         }
 
-        let msg = QueryMsg::GetMatch { match_id };
+        let msg = QueryMsg::GetMatch { address: HumanAddr("bob".into()), key: "bob_key".into(), match_id };
         let res = query(&deps, msg).unwrap();
         let game_match: MatchResponse = from_binary(&res).unwrap();
-        assert_eq!(game_match.attempts, 1);
+        assert_eq!(game_match.pairs, vec![2, 2]);
+        assert_eq!(game_match.winner, None);
+        assert!(game_match.is_draw);
     }
 
     #[test]
-    fn reveal_card_hit() {
+    fn lobby() {
         let mut deps = mock_dependencies(20, &[]);
 
-        let msg = InitMsg { entropy: 100 };
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
         let env = mock_env("creator", &[]);
         let _res = init(&mut deps, env, msg).unwrap();
 
-        let env = mock_env("player", &[]);
-        let msg = HandleMsg::StartMatch {
-            entropy: 200,
-            rows: 4,
-            cols: 4,
+        let env = mock_env("alice", &[]);
+        let msg = HandleMsg::OpenGame { entropy: 200, rows: 4, cols: 4, max_players: 1 };
+        let res = handle(&mut deps, env, msg);
+        assert_eq!(res.unwrap_err(), ContractError::InvalidMaxPlayers { min: MIN_PLAYERS, max: MAX_PLAYERS }.into());
+
+        let mut match_ids = vec![];
+        for max_players in [3, 2] {
+            let env = mock_env("alice", &[]);
+            let msg = HandleMsg::OpenGame { entropy: 200, rows: 4, cols: 4, max_players };
+            let res = handle(&mut deps, env, msg).unwrap();
+            match_ids.push(from_binary::<String>(&res.data.unwrap()).unwrap());
+        }
+
+        let list = |deps: &Extern<MockStorage, MockApi, MockQuerier>, start, limit| {
+            let msg = QueryMsg::ListOpenGames { start, limit };
+            let res = query(deps, msg).unwrap();
+            from_binary::<OpenGamesResponse>(&res).unwrap().games
         };
+        let games = list(&deps, None, None);
+        assert_eq!(games, vec![
+            OpenGameSummary { match_id: match_ids[0].clone(), size: (4, 4), players: 1, max_players: 3 },
+            OpenGameSummary { match_id: match_ids[1].clone(), size: (4, 4), players: 1, max_players: 2 },
+        ]);
+        assert_eq!(list(&deps, Some(1), Some(1)).len(), 1);
+
+        let join = |deps: &mut Extern<MockStorage, MockApi, MockQuerier>, player: &str, match_id: &str| {
+            let env = mock_env(player, &[]);
+            let msg = HandleMsg::JoinGame { match_id: match_id.into() };
+            handle(deps, env, msg)
+        };
+        let res = join(&mut deps, "alice", &match_ids[0]);
+        assert_eq!(res.unwrap_err(), ContractError::AlreadyJoined.into());
+        let _res = join(&mut deps, "bob", &match_ids[0]).unwrap();
+        assert_eq!(list(&deps, None, None)[0].players, 2);
+
+        let res = reveal(&mut deps, "alice", &match_ids[0], (0, 0));
+        assert_eq!(res.unwrap_err(), ContractError::MatchNotStarted.into());
+        let env = mock_env("bob", &[]);
+        let msg = HandleMsg::AbandonMatch { match_id: match_ids[0].clone() };
+        let res = handle(&mut deps, env, msg);
+        assert_eq!(res.unwrap_err(), ContractError::MatchNotStarted.into());
+
+        // The last seat starts the game and takes it out of the lobby.
+        let _res = join(&mut deps, "carol", &match_ids[0]).unwrap();
+        let game_match = storage_match_read(&deps.storage).load(match_ids[0].as_bytes()).unwrap();
+        assert_eq!(game_match.status, MatchStatus::InProgress);
+        assert_eq!(game_match.players.len(), 3);
+        assert_eq!(game_match.pairs, vec![0, 0, 0]);
+        let res = join(&mut deps, "dave", &match_ids[0]);
+        assert_eq!(res.unwrap_err(), ContractError::GameNotOpen.into());
+        let games = list(&deps, None, None);
+        assert_eq!(games.len(), 1);
+        assert_eq!(games[0].match_id, match_ids[1]);
+
+        let carol = storage_player_read(&deps.storage).load(deps.api.canonical_address(&HumanAddr("carol".into())).unwrap().as_slice()).unwrap();
+        assert_eq!((carol.match_count, carol.matches_started), (1, 1));
+
+        let env = mock_env("bob", &[]);
+        let msg = HandleMsg::CancelGame { match_id: match_ids[1].clone() };
+        let res = handle(&mut deps, env, msg);
+        assert_eq!(res.unwrap_err(), ContractError::NotYourMatch.into());
+
+        let env = mock_env("alice", &[]);
+        let msg = HandleMsg::CancelGame { match_id: match_ids[1].clone() };
+        let _res = handle(&mut deps, env.clone(), msg.clone()).unwrap();
+        let game_match = storage_match_read(&deps.storage).load(match_ids[1].as_bytes()).unwrap();
+        assert_eq!(game_match.status, MatchStatus::Cancelled);
+        assert!(list(&deps, None, None).is_empty());
+        let res = handle(&mut deps, env, msg);
+        assert_eq!(res.unwrap_err(), ContractError::GameNotOpen.into());
+        let res = join(&mut deps, "bob", &match_ids[1]);
+        assert_eq!(res.unwrap_err(), ContractError::GameNotOpen.into());
+    }
+
+    #[test]
+    fn lobby_abandon() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        for player in ["alice", "bob", "carol"] {
+            let env = mock_env(player, &[]);
+            let msg = HandleMsg::SetViewingKey { key: format!("{}_key", player) };
+            let _res = handle(&mut deps, env, msg).unwrap();
+        }
+
+        let env = mock_env("alice", &[]);
+        let msg = HandleMsg::OpenGame { entropy: 200, rows: 4, cols: 4, max_players: 3 };
         let res = handle(&mut deps, env, msg).unwrap();
         let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+        for player in ["bob", "carol"] {
+            let env = mock_env(player, &[]);
+            let msg = HandleMsg::JoinGame { match_id: match_id.clone() };
+            let _res = handle(&mut deps, env, msg).unwrap();
+        }
+        deal(&mut deps, "alice", &match_id);
 
-        let env = mock_env("player", &[]);
-        let msg = HandleMsg::RevealCard {
-            entropy: 300,
-            match_id: match_id.clone(),
-            pos: (2, 2),
+        // Alice takes a pair and misses, and Bob leaves in the middle of his turn.
+        let pairs = pair_positions(&deps, &match_id);
+        for pos in [pairs[0][0], pairs[0][1], pairs[1][0], pairs[2][0]] {
+            let _res = reveal(&mut deps, "alice", &match_id, pos).unwrap();
+        }
+        let _res = reveal(&mut deps, "bob", &match_id, pairs[1][0]).unwrap();
+        let env = later_env("bob");
+        let msg = HandleMsg::AbandonMatch { match_id: match_id.clone() };
+        let _res = handle(&mut deps, env.clone(), msg.clone()).unwrap();
+        assert_eq!(handle(&mut deps, env, msg).unwrap_err(), ContractError::AlreadyLeft.into());
+
+        let get_match = |deps: &Extern<MockStorage, MockApi, MockQuerier>| {
+            let msg = QueryMsg::GetMatch { address: HumanAddr("alice".into()), key: "alice_key".into(), match_id: match_id.clone() };
+            from_binary::<MatchResponse>(&query(deps, msg).unwrap()).unwrap()
         };
-        let res = handle(&mut deps, env, msg.clone()).unwrap();
-        let card: CardResponse = from_binary(&res.data.unwrap()).unwrap();
+        let game_match = get_match(&deps);
+        assert_eq!(game_match.status, MatchStatus::InProgress);
+        assert_eq!(game_match.left, vec![HumanAddr("bob".into())]);
+        assert_eq!(game_match.turn, Some(HumanAddr("carol".into())));
+        assert_eq!(game_match.cards.iter().flatten().filter(|card| card.is_some()).count(), 2);
 
-        let env = mock_env("player", &[]);
-        let msg = HandleMsg::RevealCard {
-            entropy: 400,
-            match_id: match_id.clone(),
-            pos: (3, 0),
+        // The others play on, and Bob's seat is skipped from then on.
+        let _res = reveal(&mut deps, "carol", &match_id, pairs[1][0]).unwrap();
+        let _res = reveal(&mut deps, "carol", &match_id, pairs[2][0]).unwrap();
+        assert_eq!(get_match(&deps).turn, Some(HumanAddr("alice".into())));
+        assert_eq!(reveal(&mut deps, "bob", &match_id, pairs[1][0]).unwrap_err(), ContractError::NotYourTurn.into());
+        for pair in &pairs[1..] {
+            for pos in pair {
+                let _res = reveal(&mut deps, "alice", &match_id, *pos).unwrap();
+            }
+        }
+        let game_match = get_match(&deps);
+        assert_eq!(game_match.status, MatchStatus::Completed);
+        assert_eq!(game_match.winner, Some(HumanAddr("alice".into())));
+
+        let record = |deps: &Extern<MockStorage, MockApi, MockQuerier>, player: &str| {
+            let msg = QueryMsg::GetPlayer { address: HumanAddr(player.into()), key: format!("{}_key", player) };
+            let player: PlayerResponse = from_binary(&query(deps, msg).unwrap()).unwrap();
+            (player.matches_completed, player.matches_abandoned, player.win_streak)
         };
-        let res = handle(&mut deps, env, msg.clone()).unwrap();
-        let card2: CardResponse = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!(record(&deps, "alice"), (1, 0, 1));
+        assert_eq!(record(&deps, "bob"), (0, 1, 0));
+        assert_eq!(record(&deps, "carol"), (1, 0, 0));
+    }
 
-        assert!(card.shape == card2.shape && card.color == card2.color, "Cards must match.");
+    #[test]
+    fn claim_timeout() {
+        let mut deps = mock_dependencies(20, &[]);
 
-        let msg = QueryMsg::GetCard { match_id: match_id.clone(), row: 2, col: 2 };
-        let res = query(&deps, msg).unwrap();
-        let card: CardResponse = from_binary(&res).unwrap();
-        assert_eq!(card.shape, Shape::Pentagon);
-        assert_eq!(card.color, Color::Red);
-        assert_eq!(card.pos, (2, 2));
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
 
-        let msg = QueryMsg::GetCard { match_id: match_id.clone(), row: 3, col: 0 };
-        let res = query(&deps, msg).unwrap();
-        let card: CardResponse = from_binary(&res).unwrap();
-        assert_eq!(card.shape, Shape::Pentagon);
-        assert_eq!(card.color, Color::Red);
-        assert_eq!(card.pos, (3, 0));
+        let env = mock_env("creator", &[]);
+        let msg = HandleMsg::UpdateConfig { board_limits: None, leaderboard_size: None, match_timeout: Some(0), wager: None, randomness: None };
+        assert_eq!(handle(&mut deps, env.clone(), msg).unwrap_err(), ContractError::InvalidMatchTimeout.into());
+        let msg = HandleMsg::UpdateConfig { board_limits: None, leaderboard_size: None, match_timeout: Some(10), wager: None, randomness: None };
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("alice", &[]);
+        let msg = HandleMsg::SetViewingKey { key: "alice_key".into() };
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("alice", &[]);
+        let msg = HandleMsg::StartMatch { entropy: 200, rows: 2, cols: 2 };
+        let res = handle(&mut deps, env, msg).unwrap();
+        let solo_id: String = from_binary(&res.data.unwrap()).unwrap();
+        let challenge_id = create_challenge(&mut deps, 2, 2);
+        let env = mock_env("bob", &[]);
+        let msg = HandleMsg::AcceptChallenge { match_id: challenge_id.clone() };
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let claim = |deps: &mut Extern<MockStorage, MockApi, MockQuerier>, match_id: &str, height: u64| {
+            let mut env = mock_env("anyone", &[]);
+            env.block.height = height;
+            let msg = HandleMsg::ClaimTimeout { match_id: match_id.into() };
+            handle(deps, env, msg)
+        };
+        let res = claim(&mut deps, &solo_id, 12_354);
+        assert_eq!(res.unwrap_err(), ContractError::MatchNotExpired { expires_at_height: 12_355 }.into());
+
+        // Any move pushes the deadline back.
+        let mut env = mock_env("alice", &[]);
+        env.block.height = 12_350;
+        let msg = HandleMsg::RevealCard { entropy: 0, match_id: solo_id.clone(), pos: (0, 0) };
+        let _res = handle(&mut deps, env, msg).unwrap();
+        let res = claim(&mut deps, &solo_id, 12_355);
+        assert_eq!(res.unwrap_err(), ContractError::MatchNotExpired { expires_at_height: 12_360 }.into());
+
+        let _res = claim(&mut deps, &solo_id, 12_360).unwrap();
+        let res = claim(&mut deps, &solo_id, 12_360);
+        assert_eq!(res.unwrap_err(), ContractError::MatchFinished.into());
+        let mut env = mock_env("alice", &[]);
+        env.block.height = 12_361;
+        let msg = HandleMsg::RevealCard { entropy: 0, match_id: solo_id.clone(), pos: (0, 1) };
+        assert_eq!(handle(&mut deps, env, msg).unwrap_err(), ContractError::MatchFinished.into());
+
+        // Alice moved last in the challenge, so Bob is the one who stalled it.
+        let pairs = pair_positions(&deps, &challenge_id);
+        let _res = reveal(&mut deps, "alice", &challenge_id, pairs[0][0]).unwrap();
+        let _res = reveal(&mut deps, "alice", &challenge_id, pairs[1][0]).unwrap();
+        let _res = claim(&mut deps, &challenge_id, 12_356).unwrap();
 
-        let msg = QueryMsg::GetMatch { match_id };
+        let msg = QueryMsg::GetMatch { address: HumanAddr("alice".into()), key: "alice_key".into(), match_id: challenge_id };
         let res = query(&deps, msg).unwrap();
         let game_match: MatchResponse = from_binary(&res).unwrap();
-        assert_eq!(game_match.attempts, 0);
+        assert_eq!(game_match.status, MatchStatus::Expired);
+        assert_eq!(game_match.forfeited_by, Some(HumanAddr("bob".into())));
+        assert_eq!(game_match.winner, Some(HumanAddr("alice".into())));
+        assert_eq!(game_match.expires_at_height, None);
+
+        let msg = QueryMsg::GetPlayer { address: HumanAddr("alice".into()), key: "alice_key".into() };
+        let player: PlayerResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert_eq!((player.matches_started, player.matches_completed, player.matches_abandoned, player.matches_expired), (2, 1, 0, 1));
+        assert_eq!(player.win_streak, 1);
+
+        let msg = QueryMsg::GetMatches { address: HumanAddr("alice".into()), key: "alice_key".into(), start_after: None, limit: None };
+        let history: MatchesResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert!(history.matches.iter().all(|summary| summary.status == MatchStatus::Expired));
+    }
+
+    #[test]
+    fn wagered_match() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let env = mock_env("player", &coins(10_000_000, "uscrt"));
+        assert_eq!(handle(&mut deps, env, HandleMsg::FundBankroll {}).unwrap_err(), ContractError::NotAdmin.into());
+        let env = mock_env("creator", &coins(10_000_000, "uscrt"));
+        let _res = handle(&mut deps, env, HandleMsg::FundBankroll {}).unwrap();
+
+        let start = |deps: &mut Extern<MockStorage, MockApi, MockQuerier>, funds: &[Coin], rows, cols| -> StdResult<String> {
+            let env = mock_env("player", funds);
+            let msg = HandleMsg::StartMatch { entropy: 200, rows, cols };
+            let res = handle(deps, env, msg)?;
+            let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+            deal(deps, "player", &match_id);
+            Ok(match_id)
+        };
+        let res = start(&mut deps, &coins(1_000_000, "uatom"), 4, 4);
+        assert_eq!(res.unwrap_err(), ContractError::InvalidStake { denom: "uscrt".into() }.into());
+        let res = start(&mut deps, &coins(10, "uscrt"), 4, 4);
+        assert_eq!(res.unwrap_err(), ContractError::StakeOutOfRange { min: Uint128(1_000_000), max: Uint128(100_000_000) }.into());
+        let res = start(&mut deps, &coins(1_000_000, "uscrt"), 2, 2);
+        assert_eq!(res.unwrap_err(), ContractError::WagerBoardTooSmall { min_pairs: 8 }.into());
+        let res = start(&mut deps, &coins(6_000_000, "uscrt"), 4, 4);
+        assert_eq!(res.unwrap_err(), ContractError::InsufficientBankroll.into());
+
+        let bankroll = |deps: &Extern<MockStorage, MockApi, MockQuerier>| {
+            let res = query(deps, QueryMsg::GetBankroll {}).unwrap();
+            let bankroll: BankrollResponse = from_binary(&res).unwrap();
+            (bankroll.available.u128(), bankroll.reserved.u128())
+        };
+        let match_id = start(&mut deps, &coins(1_000_000, "uscrt"), 4, 4).unwrap();
+        assert_eq!(bankroll(&deps), (8_000_000, 3_000_000));
+
+        let mut env = later_env("player");
+        env.message.sent_funds = coins(1_000_000, "uscrt");
+        let msg = HandleMsg::RevealCard { entropy: 0, match_id: match_id.clone(), pos: (0, 0) };
+        assert_eq!(handle(&mut deps, env, msg).unwrap_err(), ContractError::UnexpectedFunds.into());
+
+        // A perfect game pays three times the stake, minus the 3% fee.
+        let mut res = HandleResponse::default();
+        for pair in pair_positions(&deps, &match_id) {
+            let _res = reveal(&mut deps, "player", &match_id, pair[0]).unwrap();
+            res = reveal(&mut deps, "player", &match_id, pair[1]).unwrap();
+        }
+        assert_eq!(res.messages, vec![CosmosMsg::Bank(BankMsg::Send {
+            from_address: HumanAddr(MOCK_CONTRACT_ADDR.into()),
+            to_address: HumanAddr("player".into()),
+            amount: coins(2_910_000, "uscrt"),
+        })]);
+        assert_eq!(bankroll(&deps), (8_090_000, 0));
+        let game_match = storage_match_read(&deps.storage).load(match_id.as_bytes()).unwrap();
+        assert_eq!(game_match.wager.unwrap().payout, Some(Uint128(2_910_000)));
+
+        // Abandoning forfeits the stake to the bankroll.
+        let match_id = start(&mut deps, &coins(1_000_000, "uscrt"), 4, 4).unwrap();
+        let env = later_env("player");
+        let _res = handle(&mut deps, env, HandleMsg::AbandonMatch { match_id }).unwrap();
+        assert_eq!(bankroll(&deps), (9_090_000, 0));
+
+        let _match_id = start(&mut deps, &coins(1_000_000, "uscrt"), 4, 4).unwrap();
+        let env = mock_env("creator", &[]);
+        let msg = HandleMsg::WithdrawBankroll { amount: Uint128(7_090_001), recipient: None };
+        assert_eq!(handle(&mut deps, env, msg).unwrap_err(), ContractError::InsufficientBankroll.into());
+        let env = mock_env("creator", &[]);
+        let msg = HandleMsg::WithdrawBankroll { amount: Uint128(7_090_000), recipient: Some(HumanAddr("treasury".into())) };
+        let res = handle(&mut deps, env, msg).unwrap();
+        assert_eq!(res.messages, vec![CosmosMsg::Bank(BankMsg::Send {
+            from_address: HumanAddr(MOCK_CONTRACT_ADDR.into()),
+            to_address: HumanAddr("treasury".into()),
+            amount: coins(7_090_000, "uscrt"),
+        })]);
+        assert_eq!(bankroll(&deps), (0, 3_000_000));
+    }
+
+    #[test]
+    fn token_entry_fees() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let token = ContractInfo { address: HumanAddr("token".into()), code_hash: "token_hash".into() };
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: Some(token) };
+        let env = mock_env("creator", &[]);
+        let res = init(&mut deps, env, msg).unwrap();
+        assert_eq!(res.messages, vec![snip20::register_receive_msg("".into(), None, BLOCK_SIZE, "token_hash".into(), HumanAddr("token".into())).unwrap()]);
+
+        let schedule = TokenSchedule { entry_fee: Uint128(100), prizes: vec![PrizeTier { max_misses: 4, prize: Uint128(500) }] };
+        let msg = HandleMsg::SetTokenSchedule { rows: 4, cols: 4, schedule: Some(schedule.clone()) };
+        let env = mock_env("player", &[]);
+        assert_eq!(handle(&mut deps, env, msg.clone()).unwrap_err(), ContractError::NotAdmin.into());
+        let env = mock_env("creator", &[]);
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let send = |deps: &mut Extern<MockStorage, MockApi, MockQuerier>, token: &str, from: &str, amount, msg: &ReceiveMsg| {
+            let env = mock_env(token, &[]);
+            let msg = HandleMsg::Receive { sender: HumanAddr(from.into()), from: HumanAddr(from.into()), amount: Uint128(amount), msg: Some(to_binary(msg).unwrap()) };
+            handle(deps, env, msg)
+        };
+        let res = send(&mut deps, "fake_token", "creator", 1_000, &ReceiveMsg::FundPrizePool {});
+        assert_eq!(res.unwrap_err(), ContractError::UnknownToken.into());
+        let _res = send(&mut deps, "token", "creator", 1_000, &ReceiveMsg::FundPrizePool {}).unwrap();
+
+        let res = send(&mut deps, "token", "player", 100, &ReceiveMsg::StartMatch { entropy: 200, rows: 2, cols: 2 });
+        assert_eq!(res.unwrap_err(), ContractError::NoTokenSchedule { rows: 2, cols: 2 }.into());
+        let res = send(&mut deps, "token", "player", 50, &ReceiveMsg::StartMatch { entropy: 200, rows: 4, cols: 4 });
+        assert_eq!(res.unwrap_err(), ContractError::WrongEntryFee { expected: Uint128(100) }.into());
+
+        let res = send(&mut deps, "token", "player", 100, &ReceiveMsg::StartMatch { entropy: 200, rows: 4, cols: 4 }).unwrap();
+        let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+        deal(&mut deps, "player", &match_id);
+        let game_match = storage_match_read(&deps.storage).load(match_id.as_bytes()).unwrap();
+        assert_eq!(game_match.players, vec![deps.api.canonical_address(&HumanAddr("player".into())).unwrap()]);
+
+        let mut res = HandleResponse::default();
+        for pair in pair_positions(&deps, &match_id) {
+            let _res = reveal(&mut deps, "player", &match_id, pair[0]).unwrap();
+            res = reveal(&mut deps, "player", &match_id, pair[1]).unwrap();
+        }
+        assert_eq!(res.messages, vec![snip20::transfer_msg(HumanAddr("player".into()), Uint128(500), None, None, BLOCK_SIZE, "token_hash".into(), HumanAddr("token".into())).unwrap()]);
+
+        let msg = QueryMsg::GetTokenSchedule { rows: 4, cols: 4 };
+        let res: TokenScheduleResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert_eq!(res.schedule, Some(schedule));
+        assert_eq!(res.prize_pool, Uint128(600));
+        let game_match = storage_match_read(&deps.storage).load(match_id.as_bytes()).unwrap();
+        assert_eq!(game_match.token_entry, Some(TokenEntry { entry_fee: Uint128(100), prize: Some(Uint128(500)) }));
+
+        // While matches are stopped, the prize pool can still be funded.
+        let env = mock_env("creator", &[]);
+        let msg = HandleMsg::SetContractStatus { status: ContractStatus::StopStartingMatches };
+        let _res = handle(&mut deps, env, msg).unwrap();
+        let res = send(&mut deps, "token", "player", 100, &ReceiveMsg::StartMatch { entropy: 200, rows: 4, cols: 4 });
+        assert_eq!(res.unwrap_err(), ContractError::MatchesStopped.into());
+        let _res = send(&mut deps, "token", "creator", 1_000, &ReceiveMsg::FundPrizePool {}).unwrap();
+        let msg = QueryMsg::GetTokenSchedule { rows: 4, cols: 4 };
+        let res: TokenScheduleResponse = from_binary(&query(&deps, msg).unwrap()).unwrap();
+        assert_eq!(res.prize_pool, Uint128(1_600));
+    }
+
+    #[test]
+    fn achievements() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let nft = ContractInfo { address: HumanAddr("nft".into()), code_hash: "nft_hash".into() };
+        let mut achievements = Achievement::defaults();
+        achievements[2].kind = AchievementKind::MatchCount { count: 2 };
+        let msg = HandleMsg::SetAchievements { nft: Some(nft.clone()), achievements: achievements.clone() };
+        let env = mock_env("player", &[]);
+        assert_eq!(handle(&mut deps, env, msg.clone()).unwrap_err(), ContractError::NotAdmin.into());
+        let env = mock_env("creator", &[]);
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        let mut duplicated = achievements.clone();
+        duplicated[1].kind = AchievementKind::Flawless;
+        let mut unnamed = achievements.clone();
+        unnamed[2].name = String::new();
+        for invalid in [duplicated, unnamed] {
+            let msg = HandleMsg::SetAchievements { nft: Some(nft.clone()), achievements: invalid };
+            let env = mock_env("creator", &[]);
+            assert_eq!(handle(&mut deps, env, msg).unwrap_err(), ContractError::InvalidAchievements.into());
+        }
+
+        let start = |deps: &mut Extern<MockStorage, MockApi, MockQuerier>| {
+            let env = mock_env("player", &[]);
+            let msg = HandleMsg::StartMatch { entropy: 200, rows: 2, cols: 2 };
+            let res = handle(deps, env, msg).unwrap();
+            let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+            deal(deps, "player", &match_id);
+            match_id
+        };
+
+        // One miss on the first board earns nothing.
+        let match_id = start(&mut deps);
+        let pairs = pair_positions(&deps, &match_id);
+        let _res = reveal(&mut deps, "player", &match_id, pairs[0][0]).unwrap();
+        let _res = reveal(&mut deps, "player", &match_id, pairs[1][0]).unwrap();
+        let mut res = HandleResponse::default();
+        for pair in &pairs {
+            let _res = reveal(&mut deps, "player", &match_id, pair[0]).unwrap();
+            res = reveal(&mut deps, "player", &match_id, pair[1]).unwrap();
+        }
+        assert!(res.messages.is_empty());
+
+        // A flawless second board beats the first score and is the second match.
+        let match_id = start(&mut deps);
+        for pair in pair_positions(&deps, &match_id) {
+            let _res = reveal(&mut deps, "player", &match_id, pair[0]).unwrap();
+            res = reveal(&mut deps, "player", &match_id, pair[1]).unwrap();
+        }
+        let game_match = storage_match_read(&deps.storage).load(match_id.as_bytes()).unwrap();
+        let expected: Vec<CosmosMsg> = achievements
+            .iter()
+            .map(|achievement| mint_achievement_msg(&nft, achievement, HumanAddr("player".into()), &match_id, &game_match, BLOCK_SIZE).unwrap())
+            .collect();
+        assert_eq!(res.messages, expected);
+
+        match &res.messages[0] {
+            CosmosMsg::Wasm(WasmMsg::Execute { contract_addr, msg, .. }) => {
+                assert_eq!(contract_addr, &HumanAddr("nft".into()));
+                let msg = String::from_utf8(msg.0.clone()).unwrap();
+                assert!(msg.contains(&format!("{}-flawless", match_id)));
+                assert!(msg.contains(r#"\"score\":"#));
+                assert!(msg.contains(r#"\"board\":[["#));
+            }
+            _ => panic!("Must mint through the nft contract"),
+        }
+    }
+
+    #[test]
+    fn match_count_achievement() {
+        let mut deps = mock_dependencies(20, &[]);
+
+        let msg = InitMsg { entropy: 100, admin: None, board_limits: None, token: None };
+        let env = mock_env("creator", &[]);
+        let _res = init(&mut deps, env, msg).unwrap();
+
+        let solo = |deps: &mut Extern<MockStorage, MockApi, MockQuerier>| {
+            let env = mock_env("alice", &[]);
+            let msg = HandleMsg::StartMatch { entropy: 200, rows: 2, cols: 2 };
+            let res = handle(deps, env, msg).unwrap();
+            let match_id: String = from_binary(&res.data.unwrap()).unwrap();
+            deal(deps, "alice", &match_id);
+            let mut res = HandleResponse::default();
+            for pair in pair_positions(deps, &match_id) {
+                let _res = reveal(deps, "alice", &match_id, pair[0]).unwrap();
+                res = reveal(deps, "alice", &match_id, pair[1]).unwrap();
+            }
+            res
+        };
+
+        // Alice completes her first match before any NFT contract is set.
+        let _res = solo(&mut deps);
+
+        let nft = ContractInfo { address: HumanAddr("nft".into()), code_hash: "nft_hash".into() };
+        let achievement = Achievement { kind: AchievementKind::MatchCount { count: 1 }, name: "First".into(), description: "Completed a match.".into() };
+        let msg = HandleMsg::SetAchievements { nft: Some(nft.clone()), achievements: vec![achievement.clone()] };
+        let env = mock_env("creator", &[]);
+        let _res = handle(&mut deps, env, msg).unwrap();
+
+        // Completing a head-to-head match awards both players, including Alice
+        // who is already past the count.
+        let match_id = create_challenge(&mut deps, 2, 2);
+        let env = mock_env("bob", &[]);
+        let msg = HandleMsg::AcceptChallenge { match_id: match_id.clone() };
+        let _res = handle(&mut deps, env, msg).unwrap();
+        let mut res = HandleResponse::default();
+        for pair in pair_positions(&deps, &match_id) {
+            let _res = reveal(&mut deps, "alice", &match_id, pair[0]).unwrap();
+            res = reveal(&mut deps, "alice", &match_id, pair[1]).unwrap();
+        }
+        let game_match = storage_match_read(&deps.storage).load(match_id.as_bytes()).unwrap();
+        let expected: Vec<CosmosMsg> = ["alice", "bob"]
+            .iter()
+            .map(|player| mint_achievement_msg(&nft, &achievement, HumanAddr((*player).into()), &match_id, &game_match, BLOCK_SIZE).unwrap())
+            .collect();
+        assert_eq!(res.messages, expected);
+        let token_ids: Vec<String> = ["alice", "bob"].iter().map(|player| achievement.token_id(&match_id, &HumanAddr((*player).into()))).collect();
+        assert_ne!(token_ids[0], token_ids[1]);
+        assert!(token_ids.iter().all(|token_id| !token_id.contains("alice") && !token_id.contains("bob")));
+
+        let res = solo(&mut deps);
+        assert!(res.messages.is_empty());
     }
 }
@@ -0,0 +1,211 @@
+use cosmwasm_std::{to_vec, StdError, Uint128};
+use serde::Serialize;
+use snafu::Snafu;
+
+#[derive(Debug, PartialEq, Snafu)]
+pub enum ContractError {
+    #[snafu(display("Board of {}x{} cards is invalid.", rows, cols))]
+    InvalidBoardSize { rows: u32, cols: u32 },
+    #[snafu(display("Board must have between {} and {} rows, got {}.", min, max, rows))]
+    RowsOutOfRange { rows: u32, min: u32, max: u32 },
+    #[snafu(display("Board must have between {} and {} columns, got {}.", min, max, cols))]
+    ColsOutOfRange { cols: u32, min: u32, max: u32 },
+    #[snafu(display("Board can't have more than {} cards.", max))]
+    TooManyCards { max: u32 },
+    #[snafu(display(
+        "Board limits must be non-zero, ordered and allow at most {} cards.",
+        max_cards
+    ))]
+    InvalidBoardLimits { max_cards: u32 },
+    #[snafu(display("No card at {:?}.", pos))]
+    OutOfBounds { pos: (u32, u32) },
+    #[snafu(display("Card at {:?} is already revealed.", pos))]
+    CardAlreadyRevealed { pos: (u32, u32) },
+    #[snafu(display("Card at {:?} is hidden.", pos))]
+    CardHidden { pos: (u32, u32) },
+    #[snafu(display("Card at {:?} can't be paired with itself.", pos))]
+    SamePosition { pos: (u32, u32) },
+    #[snafu(display("Match is finished."))]
+    MatchFinished,
+    #[snafu(display("Match doesn't belong to this player."))]
+    NotYourMatch,
+    #[snafu(display("It's not this player's turn."))]
+    NotYourTurn,
+    #[snafu(display("You already left this match."))]
+    AlreadyLeft,
+    #[snafu(display("Match hasn't started yet."))]
+    MatchNotStarted,
+    #[snafu(display("Match isn't finished yet."))]
+    MatchNotFinished,
+    #[snafu(display("Match can't be dealt before block {}.", height))]
+    DealNotReady { height: u64 },
+    #[snafu(display("Match hasn't been dealt yet."))]
+    MatchNotDealt,
+    #[snafu(display("Match has already been dealt."))]
+    MatchAlreadyDealt,
+    #[snafu(display("Challenge is no longer pending."))]
+    ChallengeNotPending,
+    #[snafu(display("Players can't challenge themselves."))]
+    CannotChallengeSelf,
+    #[snafu(display("Games must seat between {} and {} players.", min, max))]
+    InvalidMaxPlayers { min: u32, max: u32 },
+    #[snafu(display("Game isn't open for joining."))]
+    GameNotOpen,
+    #[snafu(display("Player already joined this game."))]
+    AlreadyJoined,
+    #[snafu(display("Match timeout must be at least one block."))]
+    InvalidMatchTimeout,
+    #[snafu(display("Leaderboard size must be between 1 and {}.", max))]
+    InvalidLeaderboardSize { max: u32 },
+    #[snafu(display("Match doesn't expire before block {}.", expires_at_height))]
+    MatchNotExpired { expires_at_height: u64 },
+    #[snafu(display("Stake must be sent as a single {} coin.", denom))]
+    InvalidStake { denom: String },
+    #[snafu(display("This message doesn't accept native funds."))]
+    UnexpectedFunds,
+    #[snafu(display("Stake must be between {} and {}.", min, max))]
+    StakeOutOfRange { min: Uint128, max: Uint128 },
+    #[snafu(display("Wagered boards need at least {} pairs.", min_pairs))]
+    WagerBoardTooSmall { min_pairs: u32 },
+    #[snafu(display("Bankroll can't cover this payout."))]
+    InsufficientBankroll,
+    #[snafu(display(
+        "Wager stakes must be ordered, fees at most 100% and tiers sorted by misses."
+    ))]
+    InvalidWagerConfig,
+    #[snafu(display("Token entry fees must be non-zero and prizes sorted by misses."))]
+    InvalidTokenSchedule,
+    #[snafu(display("Tokens are only accepted from the registered SNIP-20 contract."))]
+    UnknownToken,
+    #[snafu(display("Boards of {}x{} cards can't be played with tokens.", rows, cols))]
+    NoTokenSchedule { rows: u32, cols: u32 },
+    #[snafu(display("Token sends must include a message for this contract."))]
+    MissingReceiveMsg,
+    #[snafu(display("Entry fee for this board is {}.", expected))]
+    WrongEntryFee { expected: Uint128 },
+    #[snafu(display("Daily challenge of day {} was already played.", day))]
+    DailyAlreadyPlayed { day: u64 },
+    #[snafu(display("Daily challenge of day {} isn't over yet.", day))]
+    DailyNotOver { day: u64 },
+    #[snafu(display("Daily challenge of day {} is over.", day))]
+    DailyOver { day: u64 },
+    #[snafu(display("Randomness oracle must return 32 random bytes."))]
+    InvalidOracleResponse,
+    #[snafu(display("Replay is only public once the match is over and replays are enabled."))]
+    ReplayNotPublic,
+    #[snafu(display("Match {} not found.", match_id))]
+    MatchNotFound { match_id: String },
+    #[snafu(display("Player not found."))]
+    PlayerNotFound,
+    #[snafu(display("Alias must be 1 to {} letters, digits, '-' or '_'.", max_len))]
+    InvalidAlias { max_len: usize },
+    #[snafu(display("Alias \"{}\" is already taken.", alias))]
+    AliasTaken { alias: String },
+    #[snafu(display("Only the admin can do this."))]
+    NotAdmin,
+    #[snafu(display("Only the pending admin can accept the admin role."))]
+    NotPendingAdmin,
+    #[snafu(display("Starting new matches is currently stopped."))]
+    MatchesStopped,
+    #[snafu(display("Contract is currently stopped."))]
+    ContractStopped,
+    #[snafu(display("Wrong viewing key for this address or viewing key not set."))]
+    Unauthorized,
+    #[snafu(display("Every achievement needs a name and a kind of its own."))]
+    InvalidAchievements,
+    #[snafu(display("Permit doesn't apply to this contract."))]
+    PermitWrongContract,
+    #[snafu(display("Permit \"{}\" was revoked.", name))]
+    PermitRevoked { name: String },
+    #[snafu(display("Permit key type \"{}\" isn't supported.", key_type))]
+    PermitUnsupportedKey { key_type: String },
+    #[snafu(display("Failed to verify permit signature."))]
+    PermitInvalidSignature,
+    #[snafu(display("Permit doesn't grant the {} permission.", permission))]
+    PermitMissingPermission { permission: String },
+}
+
+impl ContractError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            ContractError::InvalidBoardSize { .. } => "invalid_board_size",
+            ContractError::RowsOutOfRange { .. } => "rows_out_of_range",
+            ContractError::ColsOutOfRange { .. } => "cols_out_of_range",
+            ContractError::TooManyCards { .. } => "too_many_cards",
+            ContractError::InvalidBoardLimits { .. } => "invalid_board_limits",
+            ContractError::OutOfBounds { .. } => "out_of_bounds",
+            ContractError::CardAlreadyRevealed { .. } => "card_already_revealed",
+            ContractError::CardHidden { .. } => "card_hidden",
+            ContractError::SamePosition { .. } => "same_position",
+            ContractError::MatchFinished => "match_finished",
+            ContractError::NotYourMatch => "not_your_match",
+            ContractError::NotYourTurn => "not_your_turn",
+            ContractError::AlreadyLeft => "already_left",
+            ContractError::MatchNotStarted => "match_not_started",
+            ContractError::MatchNotFinished => "match_not_finished",
+            ContractError::DealNotReady { .. } => "deal_not_ready",
+            ContractError::MatchNotDealt => "match_not_dealt",
+            ContractError::MatchAlreadyDealt => "match_already_dealt",
+            ContractError::ChallengeNotPending => "challenge_not_pending",
+            ContractError::CannotChallengeSelf => "cannot_challenge_self",
+            ContractError::InvalidMaxPlayers { .. } => "invalid_max_players",
+            ContractError::GameNotOpen => "game_not_open",
+            ContractError::AlreadyJoined => "already_joined",
+            ContractError::InvalidMatchTimeout => "invalid_match_timeout",
+            ContractError::InvalidLeaderboardSize { .. } => "invalid_leaderboard_size",
+            ContractError::MatchNotExpired { .. } => "match_not_expired",
+            ContractError::InvalidStake { .. } => "invalid_stake",
+            ContractError::UnexpectedFunds => "unexpected_funds",
+            ContractError::StakeOutOfRange { .. } => "stake_out_of_range",
+            ContractError::WagerBoardTooSmall { .. } => "wager_board_too_small",
+            ContractError::InsufficientBankroll => "insufficient_bankroll",
+            ContractError::InvalidWagerConfig => "invalid_wager_config",
+            ContractError::InvalidTokenSchedule => "invalid_token_schedule",
+            ContractError::UnknownToken => "unknown_token",
+            ContractError::NoTokenSchedule { .. } => "no_token_schedule",
+            ContractError::MissingReceiveMsg => "missing_receive_msg",
+            ContractError::WrongEntryFee { .. } => "wrong_entry_fee",
+            ContractError::DailyAlreadyPlayed { .. } => "daily_already_played",
+            ContractError::DailyNotOver { .. } => "daily_not_over",
+            ContractError::DailyOver { .. } => "daily_over",
+            ContractError::InvalidOracleResponse => "invalid_oracle_response",
+            ContractError::ReplayNotPublic => "replay_not_public",
+            ContractError::MatchNotFound { .. } => "match_not_found",
+            ContractError::PlayerNotFound => "player_not_found",
+            ContractError::InvalidAlias { .. } => "invalid_alias",
+            ContractError::AliasTaken { .. } => "alias_taken",
+            ContractError::NotAdmin => "not_admin",
+            ContractError::NotPendingAdmin => "not_pending_admin",
+            ContractError::MatchesStopped => "matches_stopped",
+            ContractError::ContractStopped => "contract_stopped",
+            ContractError::Unauthorized => "unauthorized",
+            ContractError::InvalidAchievements => "invalid_achievements",
+            ContractError::PermitWrongContract => "permit_wrong_contract",
+            ContractError::PermitRevoked { .. } => "permit_revoked",
+            ContractError::PermitUnsupportedKey { .. } => "permit_unsupported_key",
+            ContractError::PermitInvalidSignature => "permit_invalid_signature",
+            ContractError::PermitMissingPermission { .. } => "permit_missing_permission",
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct ErrorPayload<'a> {
+    code: &'a str,
+    message: String,
+}
+
+// Contract errors cross the Wasm boundary as a generic error whose message is
+// a JSON object, so clients can match on `code` instead of the message text.
+impl From<ContractError> for StdError {
+    fn from(err: ContractError) -> Self {
+        let payload = ErrorPayload {
+            code: err.code(),
+            message: err.to_string(),
+        };
+        match to_vec(&payload) {
+            Ok(msg) => StdError::generic_err(String::from_utf8_lossy(&msg)),
+            Err(err) => err,
+        }
+    }
+}
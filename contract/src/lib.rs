@@ -1,6 +1,14 @@
+pub mod achievement;
+pub mod audit;
 pub mod contract;
+pub mod error;
 pub mod msg;
+pub mod permit;
+pub mod randomness;
+pub mod score;
 pub mod state;
+pub mod viewing_key;
+pub mod wager;
 
 #[cfg(target_arch = "wasm32")]
 mod wasm {
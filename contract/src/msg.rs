@@ -1,12 +1,22 @@
-use cosmwasm_std::CanonicalAddr;
+use cosmwasm_std::{Binary, HumanAddr, Uint128};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use crate::state::{Color, Shape};
+use crate::achievement::Achievement;
+use crate::audit::CardFace;
+use crate::permit::Permit;
+use crate::randomness::RandomnessConfig;
+use crate::state::{
+    BestScore, BoardLimits, Color, ContractInfo, ContractStatus, MatchStatus, RevealOutcome, Shape,
+};
+use crate::wager::{TokenSchedule, WagerConfig};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct InitMsg {
     pub entropy: u64,
+    pub admin: Option<HumanAddr>,
+    pub board_limits: Option<BoardLimits>,
+    pub token: Option<ContractInfo>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -17,32 +27,225 @@ pub enum HandleMsg {
         rows: u32,
         cols: u32,
     },
+    StartDailyChallenge {
+        entropy: u64,
+    },
+    AddEntropy {
+        entropy: u64,
+    },
+    DealMatch {
+        entropy: u64,
+        match_id: String,
+    },
+    CreateChallenge {
+        entropy: u64,
+        opponent: HumanAddr,
+        rows: u32,
+        cols: u32,
+    },
+    AcceptChallenge {
+        match_id: String,
+    },
+    OpenGame {
+        entropy: u64,
+        rows: u32,
+        cols: u32,
+        max_players: u32,
+    },
+    JoinGame {
+        match_id: String,
+    },
+    CancelGame {
+        match_id: String,
+    },
     RevealCard {
         entropy: u64,
         match_id: String,
         pos: (u32, u32),
     },
+    AbandonMatch {
+        match_id: String,
+    },
+    ClaimTimeout {
+        match_id: String,
+    },
+    CreateViewingKey {
+        entropy: u64,
+    },
+    SetViewingKey {
+        key: String,
+    },
+    RevokePermit {
+        permit_name: String,
+    },
+    SetAlias {
+        alias: Option<String>,
+    },
+    UpdateConfig {
+        board_limits: Option<BoardLimits>,
+        leaderboard_size: Option<u32>,
+        match_timeout: Option<u64>,
+        wager: Option<WagerConfig>,
+        randomness: Option<RandomnessConfig>,
+    },
+    SetTokenSchedule {
+        rows: u32,
+        cols: u32,
+        schedule: Option<TokenSchedule>,
+    },
+    Receive {
+        sender: HumanAddr,
+        from: HumanAddr,
+        amount: Uint128,
+        msg: Option<Binary>,
+    },
+    SetAchievements {
+        nft: Option<ContractInfo>,
+        achievements: Vec<Achievement>,
+    },
+    SetPublicReplays {
+        public: bool,
+    },
+    FundBankroll {},
+    WithdrawBankroll {
+        amount: Uint128,
+        recipient: Option<HumanAddr>,
+    },
+    SetContractStatus {
+        status: ContractStatus,
+    },
+    ChangeAdmin {
+        address: HumanAddr,
+    },
+    AcceptAdmin {},
+}
+
+// Messages embedded in SNIP-20 sends to this contract.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReceiveMsg {
+    StartMatch { entropy: u64, rows: u32, cols: u32 },
+    FundPrizePool {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum QueryMsg {
     GetPlayer {
-        address: CanonicalAddr,
+        address: HumanAddr,
+        key: String,
     },
     GetCard {
+        address: HumanAddr,
+        key: String,
         match_id: String,
         row: u32,
         col: u32,
     },
     GetMatch {
+        address: HumanAddr,
+        key: String,
+        match_id: String,
+    },
+    GetMatchAudit {
+        address: HumanAddr,
+        key: String,
+        match_id: String,
+    },
+    GetMatchReplay {
         match_id: String,
+        start: Option<u32>,
+        limit: Option<u32>,
+        address: Option<HumanAddr>,
+        key: Option<String>,
+    },
+    GetMatches {
+        address: HumanAddr,
+        key: String,
+        start_after: Option<u32>,
+        limit: Option<u32>,
+    },
+    WithPermit {
+        permit: Permit,
+        query: QueryWithPermit,
+    },
+    GetConfig {},
+    GetContractStatus {},
+    GetLeaderboard {
+        rows: u32,
+        cols: u32,
+        start: Option<u32>,
+        limit: Option<u32>,
+    },
+    GetBankroll {},
+    GetTokenSchedule {
+        rows: u32,
+        cols: u32,
+    },
+    GetDailyResults {
+        day: u64,
+    },
+    ListOpenGames {
+        start: Option<u32>,
+        limit: Option<u32>,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryWithPermit {
+    GetPlayer {},
+    GetCard {
+        match_id: String,
+        row: u32,
+        col: u32,
+    },
+    GetMatch {
+        match_id: String,
+    },
+    GetMatchAudit {
+        match_id: String,
+    },
+    GetMatchReplay {
+        match_id: String,
+        start: Option<u32>,
+        limit: Option<u32>,
+    },
+    GetMatches {
+        start_after: Option<u32>,
+        limit: Option<u32>,
     },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct PlayerResponse {
-    pub matches: Vec<String>,
+    /// Number of matches in the player's history, including challenges and
+    /// lobby games that never started
+    pub match_count: u32,
+    pub alias: Option<String>,
+    /// Number of matches that actually began, whether solo, accepted
+    /// challenges or filled lobby games
+    pub matches_started: u32,
+    pub matches_completed: u32,
+    pub matches_abandoned: u32,
+    pub matches_expired: u32,
+    pub best_scores: Vec<BestScore>,
+    pub total_pairs_found: u64,
+    pub win_streak: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MatchSummary {
+    pub seq: u32,
+    pub match_id: String,
+    pub size: (u32, u32),
+    pub status: MatchStatus,
+    pub score: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MatchesResponse {
+    pub matches: Vec<MatchSummary>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -54,7 +257,126 @@ pub struct CardResponse {
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct MatchResponse {
+    pub players: Vec<HumanAddr>,
+    pub pairs: Vec<u32>,
+    pub turn: Option<HumanAddr>,
+    pub winner: Option<HumanAddr>,
+    pub is_draw: bool,
     pub size: (u32, u32),
     pub attempts: u32,
     pub cards: Vec<Vec<Option<CardResponse>>>,
+    pub score: Option<u64>,
+    pub status: MatchStatus,
+    pub completed_at_height: Option<u64>,
+    pub last_action_height: u64,
+    pub expires_at_height: Option<u64>,
+    pub forfeited_by: Option<HumanAddr>,
+    pub left: Vec<HumanAddr>,
+    pub stake: Option<Uint128>,
+    pub payout: Option<Uint128>,
+    pub entry_fee: Option<Uint128>,
+    pub prize: Option<Uint128>,
+    pub daily: Option<u64>,
+    pub commitment: Option<Binary>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MoveResponse {
+    pub seq: u32,
+    /// Index of the player in the match's turn order
+    pub player: u32,
+    pub pos: (u32, u32),
+    pub shape: Shape,
+    pub color: Color,
+    pub outcome: RevealOutcome,
+    pub height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MatchReplayResponse {
+    pub match_id: String,
+    pub size: (u32, u32),
+    pub status: MatchStatus,
+    pub move_count: u32,
+    /// Opt-in alias of each player, in turn order
+    pub aliases: Vec<Option<String>>,
+    pub moves: Vec<MoveResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct MatchAuditResponse {
+    pub match_id: String,
+    pub size: (u32, u32),
+    pub commitment: Binary,
+    pub salt: Binary,
+    pub layout: Vec<Vec<CardFace>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ConfigResponse {
+    pub admin: HumanAddr,
+    pub pending_admin: Option<HumanAddr>,
+    pub board_limits: BoardLimits,
+    pub leaderboard_size: u32,
+    pub match_timeout: u64,
+    pub wager: WagerConfig,
+    pub token: Option<ContractInfo>,
+    pub nft: Option<ContractInfo>,
+    pub achievements: Vec<Achievement>,
+    pub randomness: RandomnessConfig,
+    pub public_replays: bool,
+    pub status: ContractStatus,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DailyResultsResponse {
+    pub day: u64,
+    pub size: (u32, u32),
+    pub entries: Vec<LeaderboardEntryResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BankrollResponse {
+    pub denom: String,
+    pub available: Uint128,
+    pub reserved: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TokenScheduleResponse {
+    pub size: (u32, u32),
+    pub schedule: Option<TokenSchedule>,
+    pub prize_pool: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ContractStatusResponse {
+    pub status: ContractStatus,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LeaderboardEntryResponse {
+    pub rank: u32,
+    pub alias: Option<String>,
+    pub score: u64,
+    pub completed_at_height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LeaderboardResponse {
+    pub size: (u32, u32),
+    pub entries: Vec<LeaderboardEntryResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OpenGameSummary {
+    pub match_id: String,
+    pub size: (u32, u32),
+    pub players: u32,
+    pub max_players: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OpenGamesResponse {
+    pub games: Vec<OpenGameSummary>,
 }
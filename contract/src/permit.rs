@@ -0,0 +1,260 @@
+use std::fmt;
+
+use cosmwasm_std::{to_vec, Api, Binary, CanonicalAddr, HumanAddr, StdResult, Storage};
+use ripemd::Ripemd160;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::ContractError;
+use crate::state::storage_revoked_permit_read;
+
+pub const SECP256K1_PUBKEY_TYPE: &str = "tendermint/PubKeySecp256k1";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    Player,
+    Match,
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Permission::Player => write!(f, "player"),
+            Permission::Match => write!(f, "match"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PermitParams {
+    pub allowed_tokens: Vec<HumanAddr>,
+    pub permit_name: String,
+    pub chain_id: String,
+    pub permissions: Vec<Permission>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PubKey {
+    pub r#type: String,
+    pub value: Binary,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PermitSignature {
+    pub pub_key: PubKey,
+    pub signature: Binary,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Permit {
+    pub params: PermitParams,
+    pub signature: PermitSignature,
+}
+
+// The sign doc structs below mirror the amino JSON a wallet signs. Fields are
+// declared in alphabetical order so the serialized bytes match exactly.
+
+#[derive(Serialize)]
+struct SignedPermit {
+    account_number: String,
+    chain_id: String,
+    fee: Fee,
+    memo: String,
+    msgs: Vec<PermitMsg>,
+    sequence: String,
+}
+
+#[derive(Serialize)]
+struct Fee {
+    amount: Vec<FeeCoin>,
+    gas: String,
+}
+
+#[derive(Serialize)]
+struct FeeCoin {
+    amount: String,
+    denom: String,
+}
+
+#[derive(Serialize)]
+struct PermitMsg {
+    r#type: String,
+    value: PermitContent,
+}
+
+#[derive(Serialize)]
+struct PermitContent {
+    allowed_tokens: Vec<HumanAddr>,
+    permissions: Vec<Permission>,
+    permit_name: String,
+}
+
+impl Permit {
+    pub fn has_permission(&self, permission: &Permission) -> bool {
+        self.params.permissions.contains(permission)
+    }
+
+    pub fn sign_doc_hash(&self) -> StdResult<[u8; 32]> {
+        let signed = SignedPermit {
+            account_number: "0".to_string(),
+            chain_id: self.params.chain_id.clone(),
+            fee: Fee {
+                amount: vec![FeeCoin {
+                    amount: "0".to_string(),
+                    denom: "uscrt".to_string(),
+                }],
+                gas: "1".to_string(),
+            },
+            memo: String::new(),
+            msgs: vec![PermitMsg {
+                r#type: "query_permit".to_string(),
+                value: PermitContent {
+                    allowed_tokens: self.params.allowed_tokens.clone(),
+                    permissions: self.params.permissions.clone(),
+                    permit_name: self.params.permit_name.clone(),
+                },
+            }],
+            sequence: "0".to_string(),
+        };
+        Ok(Sha256::digest(to_vec(&signed)?).into())
+    }
+}
+
+pub fn pubkey_to_address(pubkey: &[u8]) -> CanonicalAddr {
+    let hash = Sha256::digest(pubkey);
+    CanonicalAddr(Binary(Ripemd160::digest(hash).to_vec()))
+}
+
+pub fn validate<S: Storage, A: Api>(
+    storage: &S,
+    api: &A,
+    permit: &Permit,
+    contract_address: &HumanAddr,
+) -> StdResult<CanonicalAddr> {
+    if !permit.params.allowed_tokens.contains(contract_address) {
+        return Err(ContractError::PermitWrongContract.into());
+    }
+    if permit.signature.pub_key.r#type != SECP256K1_PUBKEY_TYPE {
+        return Err(ContractError::PermitUnsupportedKey {
+            key_type: permit.signature.pub_key.r#type.clone(),
+        }
+        .into());
+    }
+
+    let pubkey = permit.signature.pub_key.value.as_slice();
+    let address = pubkey_to_address(pubkey);
+
+    let is_revoked = storage_revoked_permit_read(storage, &address)
+        .may_load(permit.params.permit_name.as_bytes())?
+        .is_some();
+    if is_revoked {
+        return Err(ContractError::PermitRevoked {
+            name: permit.params.permit_name.clone(),
+        }
+        .into());
+    }
+
+    let hash = permit.sign_doc_hash()?;
+    let is_verified = api
+        .secp256k1_verify(&hash, permit.signature.signature.as_slice(), pubkey)
+        .unwrap_or(false);
+    if !is_verified {
+        return Err(ContractError::PermitInvalidSignature.into());
+    }
+
+    Ok(address)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bech32::{ToBase32, Variant};
+    use cosmwasm_std::testing::mock_dependencies;
+    use k256::ecdsa::signature::hazmat::PrehashVerifier;
+    use k256::ecdsa::{Signature, VerifyingKey};
+
+    const CONTRACT: &str = "secret1k0jntykt7e4g3y88ltc60czgjuqdy4c9e8fzek";
+
+    // Signed with Keplr's amino signing scheme by the key whose private
+    // scalar is sha256("memory match permit").
+    fn signed_permit() -> Permit {
+        Permit {
+            params: PermitParams {
+                allowed_tokens: vec![HumanAddr(CONTRACT.into())],
+                permit_name: "memory-match".into(),
+                chain_id: "secret-4".into(),
+                permissions: vec![Permission::Player, Permission::Match],
+            },
+            signature: PermitSignature {
+                pub_key: PubKey {
+                    r#type: SECP256K1_PUBKEY_TYPE.into(),
+                    value: Binary::from_base64("Ap5cbHJt/xHAl5gY85IHtUULEgaSfqQ6R5KvpRWIYCmQ").unwrap(),
+                },
+                signature: Binary::from_base64(
+                    "6HrbVDsE+H+xSwGCATCS+rOeBQavUW3jDRl+wOV2CWN8fYleU1kUBRlYKQtrCJsCmCBCneB1qwwUnydcFIZtEA==",
+                )
+                .unwrap(),
+            },
+        }
+    }
+
+    fn is_signed(permit: &Permit) -> bool {
+        let key = VerifyingKey::from_sec1_bytes(permit.signature.pub_key.value.as_slice()).unwrap();
+        let signature = Signature::from_slice(permit.signature.signature.as_slice()).unwrap();
+        key.verify_prehash(&permit.sign_doc_hash().unwrap(), &signature)
+            .is_ok()
+    }
+
+    #[test]
+    fn sign_doc() {
+        let permit = signed_permit();
+        assert_eq!(
+            Binary(permit.sign_doc_hash().unwrap().to_vec()),
+            Binary::from_base64("j7kL53Fe48HLZ11TupWTSLnmaogYSzQQJD8YLUCiUcY=").unwrap()
+        );
+        assert!(is_signed(&permit));
+
+        let mut tampered = permit;
+        tampered.params.permissions = vec![Permission::Player];
+        assert!(!is_signed(&tampered));
+    }
+
+    #[test]
+    fn signer_address() {
+        let deps = mock_dependencies(20, &[]);
+        let permit = signed_permit();
+        let address = validate(
+            &deps.storage,
+            &deps.api,
+            &permit,
+            &HumanAddr(CONTRACT.into()),
+        )
+        .unwrap();
+        assert_eq!(
+            bech32::encode("secret", address.as_slice().to_base32(), Variant::Bech32).unwrap(),
+            "secret12z66qcye7qty3t667gs4vvt0xk5l9wz8gx90qh"
+        );
+    }
+
+    #[test]
+    fn unsupported_key_type() {
+        let deps = mock_dependencies(20, &[]);
+        let mut permit = signed_permit();
+        permit.signature.pub_key.r#type = "tendermint/PubKeyEd25519".into();
+        assert_eq!(
+            validate(
+                &deps.storage,
+                &deps.api,
+                &permit,
+                &HumanAddr(CONTRACT.into())
+            )
+            .unwrap_err(),
+            ContractError::PermitUnsupportedKey {
+                key_type: "tendermint/PubKeyEd25519".into()
+            }
+            .into()
+        );
+    }
+}
@@ -0,0 +1,74 @@
+//! Where the seeds that boards are dealt from come from.
+//!
+//! By default seeds are derived from the contract's own `Random` accumulator.
+//! A randomness oracle contract can be configured instead, in which case its
+//! output is mixed with the accumulator so neither one alone decides a board.
+
+use cosmwasm_std::{to_binary, Binary, Querier, QueryRequest, StdResult, WasmQuery};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::error::ContractError;
+use crate::state::{ContractInfo, Random};
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RandomnessConfig {
+    #[default]
+    Builtin,
+    Oracle {
+        contract: ContractInfo,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OracleQueryMsg {
+    GetRandom { domain: Binary, id: Binary },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OracleRandomResponse {
+    pub random: Binary,
+}
+
+pub trait RandomnessSource {
+    // Draws the seed for one use of the randomness, separated from every other
+    // use by `domain` and `id`.
+    fn seed<Q: Querier>(&self, querier: &Q, domain: &[u8], id: &[u8]) -> StdResult<[u8; 32]>;
+}
+
+impl RandomnessSource for Random {
+    fn seed<Q: Querier>(&self, _querier: &Q, domain: &[u8], id: &[u8]) -> StdResult<[u8; 32]> {
+        Ok(self.derive(domain, id))
+    }
+}
+
+pub struct Oracle<'a> {
+    pub contract: &'a ContractInfo,
+    pub random: &'a Random,
+}
+
+impl RandomnessSource for Oracle<'_> {
+    fn seed<Q: Querier>(&self, querier: &Q, domain: &[u8], id: &[u8]) -> StdResult<[u8; 32]> {
+        let msg = OracleQueryMsg::GetRandom {
+            domain: Binary(domain.to_vec()),
+            id: Binary(id.to_vec()),
+        };
+        let response: OracleRandomResponse =
+            querier.query(&QueryRequest::Wasm(WasmQuery::Smart {
+                contract_addr: self.contract.address.clone(),
+                callback_code_hash: self.contract.code_hash.clone(),
+                msg: to_binary(&msg)?,
+            }))?;
+        if response.random.len() != 32 {
+            return Err(ContractError::InvalidOracleResponse.into());
+        }
+
+        let mut hasher = Sha256::new();
+        hasher.update(self.random.derive(domain, id));
+        hasher.update(response.random.as_slice());
+        Ok(hasher.finalize().into())
+    }
+}
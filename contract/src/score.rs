@@ -0,0 +1,68 @@
+//! Scoring for completed matches.
+//!
+//! A match scores, for every pair found, `PAIR_POINTS` plus `SIZE_POINTS` for
+//! each pair on the board, so pairs on larger boards are worth more. The
+//! longest run of consecutive hits adds `STREAK_POINTS` for every hit after
+//! the first, and every miss costs `MISS_PENALTY`. The score never goes below
+//! zero.
+
+pub const PAIR_POINTS: u64 = 100;
+pub const SIZE_POINTS: u64 = 10;
+pub const STREAK_POINTS: u64 = 50;
+pub const MISS_PENALTY: u64 = 25;
+
+pub fn compute_score(size: (u32, u32), pairs_found: u32, misses: u32, best_streak: u32) -> u64 {
+    let total_pairs = size.0 as u64 * size.1 as u64 / 2;
+    let pair_points = pairs_found as u64 * (PAIR_POINTS + total_pairs * SIZE_POINTS);
+    let streak_points = best_streak.saturating_sub(1) as u64 * STREAK_POINTS;
+    let penalty = misses as u64 * MISS_PENALTY;
+    (pair_points + streak_points).saturating_sub(penalty)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn perfect_game() {
+        // 8 pairs worth 100 + 8 * 10 each, plus 7 streak bonuses.
+        assert_eq!(compute_score((4, 4), 8, 0, 8), 8 * 180 + 7 * 50);
+    }
+
+    #[test]
+    fn misses_reduce_score() {
+        let clean = compute_score((4, 4), 8, 0, 1);
+        let sloppy = compute_score((4, 4), 8, 6, 1);
+        assert_eq!(clean - sloppy, 6 * MISS_PENALTY);
+    }
+
+    #[test]
+    fn streaks_increase_score() {
+        assert!(compute_score((4, 4), 8, 4, 3) > compute_score((4, 4), 8, 4, 2));
+        assert_eq!(
+            compute_score((4, 4), 8, 4, 0),
+            compute_score((4, 4), 8, 4, 1)
+        );
+    }
+
+    #[test]
+    fn larger_boards_score_higher() {
+        assert!(compute_score((6, 6), 18, 0, 1) > compute_score((4, 4), 8, 0, 1));
+        assert!(compute_score((6, 6), 8, 0, 1) > compute_score((4, 4), 8, 0, 1));
+    }
+
+    #[test]
+    fn score_never_negative() {
+        assert_eq!(compute_score((2, 2), 2, 1000, 1), 0);
+        assert_eq!(compute_score((162, 1), 81, u32::MAX, 81), 0);
+    }
+
+    #[test]
+    fn no_overflow_on_largest_board() {
+        let score = compute_score((162, 1), 81, 0, 81);
+        assert_eq!(
+            score,
+            81 * (PAIR_POINTS + 81 * SIZE_POINTS) + 80 * STREAK_POINTS
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use cosmwasm_std::{CanonicalAddr, HumanAddr, StdError, StdResult, Storage};
+use cosmwasm_std::{CanonicalAddr, Env, HumanAddr, StdResult, Storage, Uint128};
 use cosmwasm_storage::{
     bucket, bucket_read, singleton, singleton_read, Bucket, ReadonlyBucket, ReadonlySingleton,
     Singleton,
@@ -9,9 +9,151 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
+use crate::achievement::Achievement;
+use crate::audit::{commitment, layout};
+use crate::error::ContractError;
+use crate::randomness::RandomnessConfig;
+use crate::wager::{Bankroll, TokenSchedule, WagerConfig};
+
+pub static CONFIG_KEY: &[u8] = b"config";
 pub static RANDOM_KEY: &[u8] = b"random";
+pub static MATCH_ID_DOMAIN: &[u8] = b"match_id";
+pub static MATCH_SEED_DOMAIN: &[u8] = b"match_seed";
+pub static DAILY_SEED_DOMAIN: &[u8] = b"daily_seed";
+pub static VIEWING_KEY_DOMAIN: &[u8] = b"viewing_key";
 pub static PLAYER_KEY: &[u8] = b"player";
+pub static ALIAS_KEY: &[u8] = b"alias";
+pub static PLAYER_MATCHES_KEY: &[u8] = b"player_matches";
 pub static MATCH_KEY: &[u8] = b"match";
+pub static MOVES_KEY: &[u8] = b"moves";
+pub static LEADERBOARD_KEY: &[u8] = b"leaderboard";
+pub static OPEN_GAMES_KEY: &[u8] = b"open_games";
+pub static OPEN_GAME_INDEX_KEY: &[u8] = b"open_game_index";
+pub static OPEN_GAME_COUNT_KEY: &[u8] = b"open_game_count";
+pub static BANKROLL_KEY: &[u8] = b"bankroll";
+pub static TOKEN_SCHEDULE_KEY: &[u8] = b"token_schedule";
+pub static PRIZE_POOL_KEY: &[u8] = b"prize_pool";
+pub static DAILY_SEED_KEY: &[u8] = b"daily_seed";
+pub static DAILY_ENTRY_KEY: &[u8] = b"daily_entry";
+pub static DAILY_RESULTS_KEY: &[u8] = b"daily_results";
+pub static CURRENT_DAY_KEY: &[u8] = b"current_day";
+pub static VIEWING_KEY_KEY: &[u8] = b"viewing_key";
+pub static REVOKED_PERMIT_KEY: &[u8] = b"revoked_permit";
+pub static CONTRACT_ADDRESS_KEY: &[u8] = b"contract_address";
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BoardLimits {
+    pub min_rows: u32,
+    pub max_rows: u32,
+    pub min_cols: u32,
+    pub max_cols: u32,
+    pub max_cards: u32,
+}
+
+impl Default for BoardLimits {
+    fn default() -> Self {
+        Self {
+            min_rows: 1,
+            max_rows: Card::DECK_SIZE as u32 * 2,
+            min_cols: 1,
+            max_cols: Card::DECK_SIZE as u32 * 2,
+            max_cards: Card::DECK_SIZE as u32 * 2,
+        }
+    }
+}
+
+impl BoardLimits {
+    pub fn check(&self) -> StdResult<()> {
+        if self.min_rows == 0
+            || self.min_cols == 0
+            || self.min_rows > self.max_rows
+            || self.min_cols > self.max_cols
+            || self.max_cards as usize > Card::DECK_SIZE * 2
+        {
+            return Err(ContractError::InvalidBoardLimits {
+                max_cards: Card::DECK_SIZE as u32 * 2,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self, rows: u32, cols: u32) -> StdResult<u32> {
+        if rows < self.min_rows || rows > self.max_rows {
+            return Err(ContractError::RowsOutOfRange {
+                rows,
+                min: self.min_rows,
+                max: self.max_rows,
+            }
+            .into());
+        }
+        if cols < self.min_cols || cols > self.max_cols {
+            return Err(ContractError::ColsOutOfRange {
+                cols,
+                min: self.min_cols,
+                max: self.max_cols,
+            }
+            .into());
+        }
+
+        let cards = rows
+            .checked_mul(cols)
+            .filter(|cards| *cards <= self.max_cards)
+            .ok_or(ContractError::TooManyCards {
+                max: self.max_cards,
+            })?;
+        if cards % 2 == 1 {
+            return Err(ContractError::InvalidBoardSize { rows, cols }.into());
+        }
+        Ok(cards)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ContractStatus {
+    Normal,
+    StopStartingMatches,
+    StopAll,
+}
+
+pub const DEFAULT_LEADERBOARD_SIZE: u32 = 100;
+// Every completion loads and saves a whole leaderboard, so its size is capped.
+pub const MAX_LEADERBOARD_SIZE: u32 = 500;
+// About a day of blocks at six seconds per block.
+pub const DEFAULT_MATCH_TIMEOUT: u64 = 14_400;
+
+// Another contract this one sends messages to or queries, such as the SNIP-20
+// token, the achievement NFT contract or a randomness oracle.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ContractInfo {
+    pub address: HumanAddr,
+    pub code_hash: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Config {
+    pub admin: CanonicalAddr,
+    pub pending_admin: Option<CanonicalAddr>,
+    pub board_limits: BoardLimits,
+    pub leaderboard_size: u32,
+    pub match_timeout: u64,
+    pub wager: WagerConfig,
+    pub token: Option<ContractInfo>,
+    pub nft: Option<ContractInfo>,
+    pub achievements: Vec<Achievement>,
+    pub randomness: RandomnessConfig,
+    pub public_replays: bool,
+    pub status: ContractStatus,
+}
+
+pub fn storage_config<S: Storage>(storage: &mut S) -> Singleton<'_, S, Config> {
+    singleton(storage, CONFIG_KEY)
+}
+
+pub fn storage_config_read<S: Storage>(storage: &S) -> ReadonlySingleton<'_, S, Config> {
+    singleton_read(storage, CONFIG_KEY)
+}
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Random {
@@ -27,39 +169,230 @@ impl Random {
         }
     }
 
-    pub fn input_entropy(&mut self, input: u64, sender: HumanAddr, block_height: u64) {
+    pub fn input_entropy(&mut self, input: u64, env: &Env) {
         let mut next_seed = self.seed.to_vec();
         next_seed.extend(input.to_be_bytes());
-        next_seed.extend(sender.0.as_bytes());
-        next_seed.extend(block_height.to_be_bytes());
+        next_seed.extend(env.message.sender.0.as_bytes());
+        next_seed.extend(env.block.height.to_be_bytes());
+        next_seed.extend(env.block.time.to_be_bytes());
+        next_seed.extend(env.contract.address.0.as_bytes());
         next_seed.extend(self.counter.to_be_bytes());
         self.seed = Sha256::digest(&next_seed).into();
         self.counter += 1;
     }
+
+    // Derives a seed for one use of the randomness. The domain and id are
+    // length-prefixed so no two uses share an input, and hashing keeps a
+    // derived seed from revealing the global seed or any other derived seed.
+    pub fn derive(&self, domain: &[u8], id: &[u8]) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update((domain.len() as u32).to_be_bytes());
+        hasher.update(domain);
+        hasher.update((id.len() as u32).to_be_bytes());
+        hasher.update(id);
+        hasher.update(self.seed);
+        hasher.finalize().into()
+    }
 }
 
-pub fn storage_random<S: Storage>(storage: &mut S) -> Singleton<S, Random> {
+pub fn storage_random<S: Storage>(storage: &mut S) -> Singleton<'_, S, Random> {
     singleton(storage, RANDOM_KEY)
 }
 
-pub fn storage_random_read<S: Storage>(storage: &S) -> ReadonlySingleton<S, Random> {
+pub fn storage_random_read<S: Storage>(storage: &S) -> ReadonlySingleton<'_, S, Random> {
     singleton_read(storage, RANDOM_KEY)
 }
 
+pub fn storage_bankroll<S: Storage>(storage: &mut S) -> Singleton<'_, S, Bankroll> {
+    singleton(storage, BANKROLL_KEY)
+}
+
+pub fn storage_bankroll_read<S: Storage>(storage: &S) -> ReadonlySingleton<'_, S, Bankroll> {
+    singleton_read(storage, BANKROLL_KEY)
+}
+
+pub fn storage_token_schedule<S: Storage>(storage: &mut S) -> Bucket<'_, S, TokenSchedule> {
+    bucket(TOKEN_SCHEDULE_KEY, storage)
+}
+
+pub fn storage_token_schedule_read<S: Storage>(
+    storage: &S,
+) -> ReadonlyBucket<'_, S, TokenSchedule> {
+    bucket_read(TOKEN_SCHEDULE_KEY, storage)
+}
+
+pub fn storage_prize_pool<S: Storage>(storage: &mut S) -> Singleton<'_, S, Uint128> {
+    singleton(storage, PRIZE_POOL_KEY)
+}
+
+pub fn storage_prize_pool_read<S: Storage>(storage: &S) -> ReadonlySingleton<'_, S, Uint128> {
+    singleton_read(storage, PRIZE_POOL_KEY)
+}
+
+pub fn storage_contract_address<S: Storage>(storage: &mut S) -> Singleton<'_, S, HumanAddr> {
+    singleton(storage, CONTRACT_ADDRESS_KEY)
+}
+
+pub fn storage_contract_address_read<S: Storage>(
+    storage: &S,
+) -> ReadonlySingleton<'_, S, HumanAddr> {
+    singleton_read(storage, CONTRACT_ADDRESS_KEY)
+}
+
+pub const MAX_ALIAS_LEN: usize = 32;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BestScore {
+    pub size: (u32, u32),
+    pub score: u64,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Player {
     pub address: CanonicalAddr,
-    pub matches: Vec<String>,
+    // Length of the match history, which also lists pending challenges and
+    // lobby games, while matches_started only counts matches that began.
+    pub match_count: u32,
+    pub alias: Option<String>,
+    pub matches_started: u32,
+    pub matches_completed: u32,
+    pub matches_abandoned: u32,
+    pub matches_expired: u32,
+    pub best_scores: Vec<BestScore>,
+    pub total_pairs_found: u64,
+    pub win_streak: u32,
+    pub match_count_awards: Vec<u32>,
+}
+
+impl Player {
+    pub fn new(address: CanonicalAddr) -> Self {
+        Self {
+            address,
+            match_count: 0,
+            alias: None,
+            matches_started: 0,
+            matches_completed: 0,
+            matches_abandoned: 0,
+            matches_expired: 0,
+            best_scores: vec![],
+            total_pairs_found: 0,
+            win_streak: 0,
+            match_count_awards: vec![],
+        }
+    }
+
+    pub fn best_score(&self, size: (u32, u32)) -> Option<u64> {
+        self.best_scores
+            .iter()
+            .find(|best| best.size == size)
+            .map(|best| best.score)
+    }
+
+    pub fn record_completed(&mut self, size: (u32, u32), score: u64) {
+        self.matches_completed += 1;
+        self.win_streak += 1;
+        match self.best_scores.iter_mut().find(|best| best.size == size) {
+            Some(best) => best.score = best.score.max(score),
+            None => self.best_scores.push(BestScore { size, score }),
+        }
+    }
+
+    // Head-to-head matches don't score, so they only count towards completed
+    // matches and the win streak.
+    pub fn record_challenge_completed(&mut self, won: bool) {
+        self.matches_completed += 1;
+        if won {
+            self.win_streak += 1;
+        } else {
+            self.win_streak = 0;
+        }
+    }
+
+    pub fn record_abandoned(&mut self) {
+        self.matches_abandoned += 1;
+        self.win_streak = 0;
+    }
+
+    pub fn record_expired(&mut self) {
+        self.matches_expired += 1;
+        self.win_streak = 0;
+    }
+
+    pub fn validate_alias(alias: &str) -> StdResult<()> {
+        let is_valid = !alias.is_empty()
+            && alias.len() <= MAX_ALIAS_LEN
+            && alias
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !is_valid {
+            return Err(ContractError::InvalidAlias {
+                max_len: MAX_ALIAS_LEN,
+            }
+            .into());
+        }
+        Ok(())
+    }
 }
 
-pub fn storage_player<S: Storage>(storage: &mut S) -> Bucket<S, Player> {
+pub fn storage_player<S: Storage>(storage: &mut S) -> Bucket<'_, S, Player> {
     bucket(PLAYER_KEY, storage)
 }
 
-pub fn storage_player_read<S: Storage>(storage: &S) -> ReadonlyBucket<S, Player> {
+pub fn storage_player_read<S: Storage>(storage: &S) -> ReadonlyBucket<'_, S, Player> {
     bucket_read(PLAYER_KEY, storage)
 }
 
+// Match history is an append-only list keyed by the player's address and the
+// big-endian sequence number of each match.
+pub fn storage_player_matches<'a, S: Storage>(
+    storage: &'a mut S,
+    address: &CanonicalAddr,
+) -> Bucket<'a, S, String> {
+    Bucket::multilevel(&[PLAYER_MATCHES_KEY, address.as_slice()], storage)
+}
+
+pub fn storage_player_matches_read<'a, S: Storage>(
+    storage: &'a S,
+    address: &CanonicalAddr,
+) -> ReadonlyBucket<'a, S, String> {
+    ReadonlyBucket::multilevel(&[PLAYER_MATCHES_KEY, address.as_slice()], storage)
+}
+
+// Aliases are indexed in lowercase so that they stay unique regardless of case.
+pub fn alias_key(alias: &str) -> Vec<u8> {
+    alias.to_ascii_lowercase().into_bytes()
+}
+
+pub fn storage_alias<S: Storage>(storage: &mut S) -> Bucket<'_, S, CanonicalAddr> {
+    bucket(ALIAS_KEY, storage)
+}
+
+pub fn storage_alias_read<S: Storage>(storage: &S) -> ReadonlyBucket<'_, S, CanonicalAddr> {
+    bucket_read(ALIAS_KEY, storage)
+}
+
+pub fn storage_viewing_key<S: Storage>(storage: &mut S) -> Bucket<'_, S, [u8; 32]> {
+    bucket(VIEWING_KEY_KEY, storage)
+}
+
+pub fn storage_viewing_key_read<S: Storage>(storage: &S) -> ReadonlyBucket<'_, S, [u8; 32]> {
+    bucket_read(VIEWING_KEY_KEY, storage)
+}
+
+pub fn storage_revoked_permit<'a, S: Storage>(
+    storage: &'a mut S,
+    address: &CanonicalAddr,
+) -> Bucket<'a, S, bool> {
+    Bucket::multilevel(&[REVOKED_PERMIT_KEY, address.as_slice()], storage)
+}
+
+pub fn storage_revoked_permit_read<'a, S: Storage>(
+    storage: &'a S,
+    address: &CanonicalAddr,
+) -> ReadonlyBucket<'a, S, bool> {
+    ReadonlyBucket::multilevel(&[REVOKED_PERMIT_KEY, address.as_slice()], storage)
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[repr(u8)]
 pub enum Shape {
@@ -74,6 +407,20 @@ pub enum Shape {
     Octagon,
 }
 
+impl Shape {
+    pub const ALL: [Shape; 9] = [
+        Shape::Triangle,
+        Shape::Square,
+        Shape::Circle,
+        Shape::Diamond,
+        Shape::Trapezoid,
+        Shape::Oval,
+        Shape::Pentagon,
+        Shape::Hexagon,
+        Shape::Octagon,
+    ];
+}
+
 impl Distribution<Shape> for Standard {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Shape {
         match rng.gen_range(0..9) {
@@ -104,6 +451,20 @@ pub enum Color {
     Black,
 }
 
+impl Color {
+    pub const ALL: [Color; 9] = [
+        Color::Red,
+        Color::Blue,
+        Color::Yellow,
+        Color::Purple,
+        Color::Green,
+        Color::Orange,
+        Color::Brown,
+        Color::Gray,
+        Color::Black,
+    ];
+}
+
 impl Distribution<Color> for Standard {
     fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> Color {
         match rng.gen_range(0..9) {
@@ -127,23 +488,253 @@ pub struct Card {
     pub is_revealed: bool,
 }
 
+impl Card {
+    pub const DECK_SIZE: usize = Shape::ALL.len() * Color::ALL.len();
+
+    pub fn deck() -> Vec<Card> {
+        Shape::ALL
+            .iter()
+            .flat_map(|shape| {
+                Color::ALL.iter().map(move |color| Card {
+                    shape: shape.clone(),
+                    color: color.clone(),
+                    is_revealed: false,
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchStatus {
+    Waiting,
+    InProgress,
+    Completed,
+    Abandoned,
+    Cancelled,
+    Expired,
+}
+
+pub const MIN_PLAYERS: u32 = 2;
+pub const MAX_PLAYERS: u32 = 8;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Wager {
+    pub stake: Uint128,
+    pub liability: Uint128,
+    pub payout: Option<Uint128>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TokenEntry {
+    pub entry_fee: Uint128,
+    pub prize: Option<Uint128>,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct Match {
-    pub player: CanonicalAddr,
+    pub players: Vec<CanonicalAddr>,
+    pub max_players: u32,
+    pub pairs: Vec<u32>,
+    pub turn: u32,
     pub size: (u32, u32),
     pub cards: Vec<Vec<Card>>,
+    pub salt: [u8; 32],
+    pub seed: [u8; 32],
+    pub commitment: [u8; 32],
+    pub requested_at_height: u64,
+    pub dealt_at_height: Option<u64>,
     pub last_reveal: Option<(u32, u32)>,
+    pub move_count: u32,
     pub attempts: u32,
+    pub pairs_found: u32,
+    pub streak: u32,
+    pub best_streak: u32,
+    pub score: Option<u64>,
+    pub status: MatchStatus,
+    pub completed_at_height: Option<u64>,
+    pub last_action_height: u64,
+    pub forfeited_by: Option<CanonicalAddr>,
+    pub left: Vec<CanonicalAddr>,
+    pub wager: Option<Wager>,
+    pub token_entry: Option<TokenEntry>,
+    pub daily: Option<u64>,
 }
 
 impl Match {
+    pub fn new(
+        players: Vec<CanonicalAddr>,
+        max_players: u32,
+        size: (u32, u32),
+        status: MatchStatus,
+        height: u64,
+    ) -> Self {
+        Self {
+            pairs: vec![0; players.len()],
+            players,
+            max_players,
+            turn: 0,
+            size,
+            cards: vec![],
+            salt: [0; 32],
+            seed: [0; 32],
+            commitment: [0; 32],
+            requested_at_height: height,
+            dealt_at_height: None,
+            last_reveal: None,
+            move_count: 0,
+            attempts: 0,
+            pairs_found: 0,
+            streak: 0,
+            best_streak: 0,
+            score: None,
+            status,
+            completed_at_height: None,
+            last_action_height: height,
+            forfeited_by: None,
+            left: vec![],
+            wager: None,
+            token_entry: None,
+            daily: None,
+        }
+    }
+
+    pub fn is_dealt(&self) -> bool {
+        self.dealt_at_height.is_some()
+    }
+
+    // The board is dealt in a later block than the one that requested it, so
+    // the layout doesn't follow from the state the request was made against.
+    pub fn deal(
+        &mut self,
+        seed: [u8; 32],
+        cards: Vec<Vec<Card>>,
+        salt: [u8; 32],
+        height: u64,
+    ) -> StdResult<()> {
+        if height <= self.requested_at_height {
+            return Err(ContractError::DealNotReady {
+                height: self.requested_at_height + 1,
+            }
+            .into());
+        }
+        self.commitment = commitment(&salt, &layout(&cards));
+        self.cards = cards;
+        self.salt = salt;
+        self.seed = seed;
+        self.dealt_at_height = Some(height);
+        Ok(())
+    }
+
+    // A daily match can only be played on its own day.
+    pub fn is_past_daily(&self, day: u64) -> bool {
+        self.daily.is_some_and(|daily| day > daily)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self.status,
+            MatchStatus::Completed
+                | MatchStatus::Abandoned
+                | MatchStatus::Cancelled
+                | MatchStatus::Expired
+        )
+    }
+
+    pub fn expires_at_height(&self, timeout: u64) -> u64 {
+        self.last_action_height.saturating_add(timeout)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.players.len() as u32 >= self.max_players
+    }
+
+    // Lobby games wait for players to join, while a waiting challenge already
+    // has its opponent seated and only waits for them to accept.
+    pub fn is_open(&self) -> bool {
+        self.status == MatchStatus::Waiting && !self.is_full()
+    }
+
+    pub fn join(&mut self, address: CanonicalAddr) {
+        self.players.push(address);
+        self.pairs.push(0);
+    }
+
+    pub fn is_solo(&self) -> bool {
+        self.players.len() == 1
+    }
+
+    pub fn has_player(&self, address: &CanonicalAddr) -> bool {
+        self.players.contains(address)
+    }
+
+    // Players who left a game of more than two players stay seated, but are
+    // skipped in the turn order and can't win it.
+    pub fn is_active(&self, address: &CanonicalAddr) -> bool {
+        !self.left.contains(address) && self.forfeited_by.as_ref() != Some(address)
+    }
+
+    pub fn active_players(&self) -> usize {
+        self.players
+            .iter()
+            .filter(|address| self.is_active(address))
+            .count()
+    }
+
+    pub fn current_player(&self) -> &CanonicalAddr {
+        &self.players[self.turn as usize]
+    }
+
+    // Passes the turn to the next player still in the game, wrapping around
+    // after the last.
+    pub fn next_turn(&mut self) {
+        loop {
+            self.turn = (self.turn + 1) % self.players.len() as u32;
+            if !self.left.contains(self.current_player()) {
+                break;
+            }
+        }
+    }
+
+    // The player with the most pairs wins a completed head-to-head match. When
+    // a player forfeits by abandoning or stalling, the win goes to whoever
+    // leads among the others. Players who left never win. A shared top count
+    // has no winner.
+    pub fn winner(&self) -> Option<&CanonicalAddr> {
+        if self.is_solo() || (self.status != MatchStatus::Completed && self.forfeited_by.is_none())
+        {
+            return None;
+        }
+        let contenders: Vec<(&CanonicalAddr, u32)> = self
+            .players
+            .iter()
+            .zip(self.pairs.iter().copied())
+            .filter(|(address, _)| self.is_active(address))
+            .collect();
+        let best = contenders.iter().map(|(_, pairs)| *pairs).max()?;
+        let mut leaders = contenders.iter().filter(|(_, pairs)| *pairs == best);
+        match (leaders.next(), leaders.next()) {
+            (Some((address, _)), None) => Some(*address),
+            _ => None,
+        }
+    }
+
+    pub fn is_draw(&self) -> bool {
+        !self.is_solo() && self.status == MatchStatus::Completed && self.winner().is_none()
+    }
+
+    pub fn all_revealed(&self) -> bool {
+        self.cards.iter().flatten().all(|card| card.is_revealed)
+    }
+
     pub fn out_of_bounds(&self, row: usize, col: usize) -> StdResult<()> {
         let (rows, cols) = self.size;
         if row as u32 >= rows || col as u32 >= cols {
-            Err(StdError::NotFound {
-                kind: "Card".to_string(),
-                backtrace: None,
-            })
+            Err(ContractError::OutOfBounds {
+                pos: (row as u32, col as u32),
+            }
+            .into())
         } else {
             Ok(())
         }
@@ -167,21 +758,163 @@ impl Match {
 
     pub fn reveal(&mut self, row: usize, col: usize) -> StdResult<()> {
         self.out_of_bounds(row, col)?;
-        let card = self
-            .cards
-            .get_mut(row as usize)
-            .unwrap()
-            .get_mut(col as usize)
-            .unwrap();
+        let card = self.cards.get_mut(row).unwrap().get_mut(col).unwrap();
         card.is_revealed = true;
         Ok(())
     }
 }
 
-pub fn storage_match<S: Storage>(storage: &mut S) -> Bucket<S, Match> {
+pub fn storage_match<S: Storage>(storage: &mut S) -> Bucket<'_, S, Match> {
     bucket(MATCH_KEY, storage)
 }
 
-pub fn storage_match_read<S: Storage>(storage: &S) -> ReadonlyBucket<S, Match> {
+pub fn storage_match_read<S: Storage>(storage: &S) -> ReadonlyBucket<'_, S, Match> {
     bucket_read(MATCH_KEY, storage)
 }
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct LeaderboardEntry {
+    pub player: CanonicalAddr,
+    pub match_id: String,
+    pub score: u64,
+    pub completed_at_height: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct Leaderboard {
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+impl Leaderboard {
+    pub fn key(size: (u32, u32)) -> Vec<u8> {
+        [size.0.to_be_bytes(), size.1.to_be_bytes()].concat()
+    }
+
+    // Keeps entries ordered by score, with earlier completions ranking first on ties.
+    pub fn insert(&mut self, entry: LeaderboardEntry, max_entries: usize) {
+        let rank = self
+            .entries
+            .iter()
+            .position(|other| {
+                entry.score > other.score
+                    || (entry.score == other.score
+                        && entry.completed_at_height < other.completed_at_height)
+            })
+            .unwrap_or(self.entries.len());
+        self.entries.insert(rank, entry);
+        self.entries.truncate(max_entries);
+    }
+}
+
+// Match ids of lobby games that are still waiting for players, keyed by the
+// big-endian position of each game. A removed game is replaced by the last one
+// so the positions stay contiguous, which means the lobby isn't kept in order.
+pub fn storage_open_games<S: Storage>(storage: &mut S) -> Bucket<'_, S, String> {
+    bucket(OPEN_GAMES_KEY, storage)
+}
+
+pub fn storage_open_games_read<S: Storage>(storage: &S) -> ReadonlyBucket<'_, S, String> {
+    bucket_read(OPEN_GAMES_KEY, storage)
+}
+
+// The position of each open game in the lobby, keyed by match id.
+pub fn storage_open_game_index<S: Storage>(storage: &mut S) -> Bucket<'_, S, u32> {
+    bucket(OPEN_GAME_INDEX_KEY, storage)
+}
+
+pub fn storage_open_game_index_read<S: Storage>(storage: &S) -> ReadonlyBucket<'_, S, u32> {
+    bucket_read(OPEN_GAME_INDEX_KEY, storage)
+}
+
+pub fn storage_open_game_count<S: Storage>(storage: &mut S) -> Singleton<'_, S, u32> {
+    singleton(storage, OPEN_GAME_COUNT_KEY)
+}
+
+pub fn storage_open_game_count_read<S: Storage>(storage: &S) -> ReadonlySingleton<'_, S, u32> {
+    singleton_read(storage, OPEN_GAME_COUNT_KEY)
+}
+
+pub fn storage_leaderboard<S: Storage>(storage: &mut S) -> Bucket<'_, S, Leaderboard> {
+    bucket(LEADERBOARD_KEY, storage)
+}
+
+pub fn storage_leaderboard_read<S: Storage>(storage: &S) -> ReadonlyBucket<'_, S, Leaderboard> {
+    bucket_read(LEADERBOARD_KEY, storage)
+}
+
+pub const SECONDS_PER_DAY: u64 = 86_400;
+pub const DAILY_BOARD_SIZE: (u32, u32) = (6, 6);
+
+pub fn day_of(time: u64) -> u64 {
+    time / SECONDS_PER_DAY
+}
+
+// Queries can't see the block time, so the day of the latest transaction
+// stands in for the current day when deciding whether a daily is over.
+pub fn storage_current_day<S: Storage>(storage: &mut S) -> Singleton<'_, S, u64> {
+    singleton(storage, CURRENT_DAY_KEY)
+}
+
+pub fn storage_current_day_read<S: Storage>(storage: &S) -> ReadonlySingleton<'_, S, u64> {
+    singleton_read(storage, CURRENT_DAY_KEY)
+}
+
+// The secret seed every daily challenge board of a day is dealt from.
+pub fn storage_daily_seed<S: Storage>(storage: &mut S) -> Bucket<'_, S, [u8; 32]> {
+    bucket(DAILY_SEED_KEY, storage)
+}
+
+pub fn storage_daily_seed_read<S: Storage>(storage: &S) -> ReadonlyBucket<'_, S, [u8; 32]> {
+    bucket_read(DAILY_SEED_KEY, storage)
+}
+
+// Each player's daily challenge match, keyed by day and then by address.
+pub fn storage_daily_entry<'a, S: Storage>(storage: &'a mut S, day: u64) -> Bucket<'a, S, String> {
+    Bucket::multilevel(&[DAILY_ENTRY_KEY, &day.to_be_bytes()], storage)
+}
+
+pub fn storage_daily_entry_read<'a, S: Storage>(
+    storage: &'a S,
+    day: u64,
+) -> ReadonlyBucket<'a, S, String> {
+    ReadonlyBucket::multilevel(&[DAILY_ENTRY_KEY, &day.to_be_bytes()], storage)
+}
+
+pub fn storage_daily_results<S: Storage>(storage: &mut S) -> Bucket<'_, S, Leaderboard> {
+    bucket(DAILY_RESULTS_KEY, storage)
+}
+
+pub fn storage_daily_results_read<S: Storage>(storage: &S) -> ReadonlyBucket<'_, S, Leaderboard> {
+    bucket_read(DAILY_RESULTS_KEY, storage)
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum RevealOutcome {
+    First,
+    Hit,
+    Miss,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Move {
+    pub player: CanonicalAddr,
+    pub pos: (u32, u32),
+    pub shape: Shape,
+    pub color: Color,
+    pub outcome: RevealOutcome,
+    pub height: u64,
+}
+
+// The move log is an append-only list keyed by the match id and the
+// big-endian sequence number of each reveal.
+pub fn storage_moves<'a, S: Storage>(storage: &'a mut S, match_id: &str) -> Bucket<'a, S, Move> {
+    Bucket::multilevel(&[MOVES_KEY, match_id.as_bytes()], storage)
+}
+
+pub fn storage_moves_read<'a, S: Storage>(
+    storage: &'a S,
+    match_id: &str,
+) -> ReadonlyBucket<'a, S, Move> {
+    ReadonlyBucket::multilevel(&[MOVES_KEY, match_id.as_bytes()], storage)
+}
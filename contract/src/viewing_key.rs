@@ -0,0 +1,26 @@
+use cosmwasm_std::Binary;
+use sha2::{Digest, Sha256};
+
+pub const VIEWING_KEY_PREFIX: &str = "api_key_";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ViewingKey(pub String);
+
+impl ViewingKey {
+    // `seed` must be derived for viewing keys alone, see `VIEWING_KEY_DOMAIN`.
+    pub fn new(seed: &[u8; 32]) -> Self {
+        Self(VIEWING_KEY_PREFIX.to_string() + &Binary::from(&seed[..]).to_base64())
+    }
+
+    pub fn to_hashed(&self) -> [u8; 32] {
+        Sha256::digest(self.0.as_bytes()).into()
+    }
+
+    pub fn check_viewing_key(&self, hashed: &[u8; 32]) -> bool {
+        ct_slice_compare(&self.to_hashed(), hashed)
+    }
+}
+
+pub fn ct_slice_compare(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
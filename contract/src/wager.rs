@@ -0,0 +1,272 @@
+//! Stakes and payouts for wagered matches.
+//!
+//! A stake is paid back multiplied by the first payout tier whose miss target
+//! the player stayed within, minus the house fee. Miss targets are given in
+//! basis points of the board's pair count, so the same tiers work for every
+//! board size. When a match starts, the bankroll reserves the largest payout
+//! the stake could win, so every payout it promises is covered.
+//!
+//! Matches paid for with the SNIP-20 token follow a schedule set per board
+//! size instead: a fixed entry fee, and fixed prizes for finishing within a
+//! number of misses. Prizes come out of the prize pool the fees go into.
+
+use cosmwasm_std::{StdResult, Uint128};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::error::ContractError;
+
+pub const STAKE_DENOM: &str = "uscrt";
+pub const BPS: u32 = 10_000;
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PayoutTier {
+    pub max_miss_ratio_bps: u32,
+    pub multiplier_bps: u32,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WagerConfig {
+    pub min_stake: Uint128,
+    pub max_stake: Uint128,
+    pub min_pairs: u32,
+    pub fee_bps: u32,
+    pub tiers: Vec<PayoutTier>,
+}
+
+impl Default for WagerConfig {
+    fn default() -> Self {
+        Self {
+            min_stake: Uint128(1_000_000),
+            max_stake: Uint128(100_000_000),
+            min_pairs: 8,
+            fee_bps: 300,
+            tiers: vec![
+                PayoutTier {
+                    max_miss_ratio_bps: 5_000,
+                    multiplier_bps: 30_000,
+                },
+                PayoutTier {
+                    max_miss_ratio_bps: 10_000,
+                    multiplier_bps: 20_000,
+                },
+                PayoutTier {
+                    max_miss_ratio_bps: 15_000,
+                    multiplier_bps: 12_000,
+                },
+            ],
+        }
+    }
+}
+
+impl WagerConfig {
+    pub fn check(&self) -> StdResult<()> {
+        let is_sorted = self
+            .tiers
+            .windows(2)
+            .all(|tiers| tiers[0].max_miss_ratio_bps < tiers[1].max_miss_ratio_bps);
+        if self.min_stake > self.max_stake || self.fee_bps > BPS || !is_sorted {
+            return Err(ContractError::InvalidWagerConfig.into());
+        }
+        Ok(())
+    }
+
+    pub fn validate(&self, stake: Uint128, size: (u32, u32)) -> StdResult<()> {
+        if stake < self.min_stake || stake > self.max_stake {
+            return Err(ContractError::StakeOutOfRange {
+                min: self.min_stake,
+                max: self.max_stake,
+            }
+            .into());
+        }
+        if (size.0 as u64 * size.1 as u64 / 2) < self.min_pairs as u64 {
+            return Err(ContractError::WagerBoardTooSmall {
+                min_pairs: self.min_pairs,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    pub fn multiplier_bps(&self, size: (u32, u32), misses: u32) -> u32 {
+        let pairs = size.0 as u64 * size.1 as u64 / 2;
+        self.tiers
+            .iter()
+            .find(|tier| misses as u64 * BPS as u64 <= tier.max_miss_ratio_bps as u64 * pairs)
+            .map_or(0, |tier| tier.multiplier_bps)
+    }
+
+    // The most a stake can win before fees, which the bankroll has to cover.
+    pub fn max_payout(&self, stake: Uint128) -> Uint128 {
+        let multiplier = self
+            .tiers
+            .iter()
+            .map(|tier| tier.multiplier_bps)
+            .max()
+            .unwrap_or(0);
+        stake.multiply_ratio(multiplier, BPS)
+    }
+
+    pub fn payout(&self, stake: Uint128, size: (u32, u32), misses: u32) -> Uint128 {
+        let gross = stake.multiply_ratio(self.multiplier_bps(size, misses), BPS);
+        let fee = gross.multiply_ratio(self.fee_bps, BPS);
+        Uint128(gross.u128() - fee.u128())
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PrizeTier {
+    pub max_misses: u32,
+    pub prize: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct TokenSchedule {
+    pub entry_fee: Uint128,
+    pub prizes: Vec<PrizeTier>,
+}
+
+impl TokenSchedule {
+    pub fn key(size: (u32, u32)) -> Vec<u8> {
+        [size.0.to_be_bytes(), size.1.to_be_bytes()].concat()
+    }
+
+    pub fn check(&self) -> StdResult<()> {
+        let is_sorted = self
+            .prizes
+            .windows(2)
+            .all(|tiers| tiers[0].max_misses < tiers[1].max_misses);
+        if self.entry_fee.is_zero() || !is_sorted {
+            return Err(ContractError::InvalidTokenSchedule.into());
+        }
+        Ok(())
+    }
+
+    pub fn prize(&self, misses: u32) -> Uint128 {
+        self.prizes
+            .iter()
+            .find(|tier| misses <= tier.max_misses)
+            .map_or(Uint128(0), |tier| tier.prize)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+pub struct Bankroll {
+    pub available: Uint128,
+    pub reserved: Uint128,
+}
+
+impl Bankroll {
+    // Takes the stake in and sets aside enough to pay out `liability`.
+    pub fn reserve(&mut self, stake: Uint128, liability: Uint128) -> StdResult<()> {
+        let funds = self.available.u128() + stake.u128();
+        if funds < liability.u128() {
+            return Err(ContractError::InsufficientBankroll.into());
+        }
+        self.available = Uint128(funds - liability.u128());
+        self.reserved += liability;
+        Ok(())
+    }
+
+    // Releases a reservation once its match is over, keeping whatever isn't
+    // paid out.
+    pub fn settle(&mut self, liability: Uint128, payout: Uint128) {
+        self.reserved = Uint128(self.reserved.u128() - liability.u128());
+        self.available += Uint128(liability.u128() - payout.u128());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tiers_scale_with_board_size() {
+        let config = WagerConfig::default();
+        assert_eq!(config.multiplier_bps((4, 4), 4), 30_000);
+        assert_eq!(config.multiplier_bps((4, 4), 5), 20_000);
+        assert_eq!(config.multiplier_bps((6, 6), 9), 30_000);
+        assert_eq!(config.multiplier_bps((4, 4), 12), 12_000);
+        assert_eq!(config.multiplier_bps((4, 4), 13), 0);
+    }
+
+    #[test]
+    fn payout_after_fee() {
+        let config = WagerConfig::default();
+        assert_eq!(
+            config.payout(Uint128(1_000_000), (4, 4), 0),
+            Uint128(2_910_000)
+        );
+        assert_eq!(config.payout(Uint128(1_000_000), (4, 4), 100), Uint128(0));
+        assert_eq!(config.max_payout(Uint128(1_000_000)), Uint128(3_000_000));
+    }
+
+    #[test]
+    fn check_config() {
+        assert!(WagerConfig::default().check().is_ok());
+
+        let config = WagerConfig {
+            fee_bps: BPS + 1,
+            ..WagerConfig::default()
+        };
+        assert!(config.check().is_err());
+
+        let mut config = WagerConfig::default();
+        config.tiers.reverse();
+        assert!(config.check().is_err());
+
+        let config = WagerConfig {
+            min_stake: Uint128(200_000_000),
+            ..WagerConfig::default()
+        };
+        assert!(config.check().is_err());
+    }
+
+    #[test]
+    fn token_prizes() {
+        let schedule = TokenSchedule {
+            entry_fee: Uint128(100),
+            prizes: vec![
+                PrizeTier {
+                    max_misses: 0,
+                    prize: Uint128(500),
+                },
+                PrizeTier {
+                    max_misses: 4,
+                    prize: Uint128(150),
+                },
+            ],
+        };
+        assert!(schedule.check().is_ok());
+        assert_eq!(schedule.prize(0), Uint128(500));
+        assert_eq!(schedule.prize(4), Uint128(150));
+        assert_eq!(schedule.prize(5), Uint128(0));
+
+        let free = TokenSchedule {
+            entry_fee: Uint128(0),
+            ..schedule
+        };
+        assert!(free.check().is_err());
+    }
+
+    #[test]
+    fn bankroll_reservations() {
+        let mut bankroll = Bankroll {
+            available: Uint128(2_000_000),
+            reserved: Uint128(0),
+        };
+        assert!(bankroll
+            .reserve(Uint128(1_000_000), Uint128(3_500_000))
+            .is_err());
+
+        bankroll
+            .reserve(Uint128(1_000_000), Uint128(3_000_000))
+            .unwrap();
+        assert_eq!(bankroll.available, Uint128(0));
+        assert_eq!(bankroll.reserved, Uint128(3_000_000));
+
+        bankroll.settle(Uint128(3_000_000), Uint128(2_910_000));
+        assert_eq!(bankroll.available, Uint128(90_000));
+        assert_eq!(bankroll.reserved, Uint128(0));
+    }
+}